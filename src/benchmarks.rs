@@ -1,11 +1,13 @@
 use colored::Colorize;
 
-use crate::{engine, game::position::Position};
+use crate::{engine, game::position::Position, macros::ternary};
 
 pub(crate) fn run() {
     println!("Running benchmarks...\n");
     bench_move_generation();
     bench_analyze_start_pos();
+    bench_search_nodes();
+    bench_thread_scaling();
 }
 
 fn benchmark(name: &str, iterations: usize, mut func: impl FnMut() -> ()) {
@@ -43,6 +45,89 @@ fn bench_analyze_start_pos() {
     let name = format!("analyze start position (d={})", max_depth);
 
     benchmark(&name, 10, || {
-        engine::run(&mut pos, max_depth, false);
+        engine::run(&mut pos, &engine::Limits::depth(max_depth), false);
     });
 }
+
+/// Counts the nodes searched to a fixed depth, with and without searching the move of the
+/// transposition table first. The effective branching factor is the ratio of the nodes of an
+/// iteration to those of the previous one, averaged over the last iterations.
+fn bench_search_nodes() {
+    const DEPTH: usize = 7;
+
+    let fens = [
+        Position::START_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1BBPPP/R2QK2R w KQ - 0 8",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    ];
+    println!("- {}", format!("search nodes (d={})", DEPTH).green());
+
+    for fen in fens {
+        println!("  * {}", fen);
+
+        for tt_move_ordering in [true, false] {
+            let mut pos = Position::from_fen(fen).unwrap();
+            let mut ctx = engine::Context::new(16);
+            let mut nodes = Vec::<u64>::new();
+            ctx.set_tt_move_ordering(tt_move_ordering);
+
+            engine::search(
+                &mut pos,
+                &mut ctx,
+                &engine::Limits::depth(DEPTH),
+                |_, iteration| nodes.push(iteration.nodes),
+            );
+
+            let label = ternary!(tt_move_ordering, "with TT move", "without TT move");
+            let branching_factor = match nodes.len() >= 4 {
+                true => {
+                    let last_nodes = &nodes[nodes.len() - 4..];
+                    let ratio = last_nodes[3] as f64 / last_nodes[0] as f64;
+                    format!("{:.2}", ratio.powf(1.0 / 3.0))
+                }
+                false => "n/a".to_string(),
+            };
+
+            println!(
+                "    {}: {} nodes, branching factor {}",
+                label,
+                nodes.last().unwrap_or(&0),
+                branching_factor
+            );
+        }
+    }
+}
+
+/// Searches the same position to a fixed depth with more and more threads. The time to depth
+/// measures what Lazy SMP actually gains, the nodes per second only how busy the threads are.
+fn bench_thread_scaling() {
+    const DEPTH: usize = 9;
+
+    let fen = "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1BBPPP/R2QK2R w KQ - 0 8";
+    let mut base_time = 0.0;
+    println!("- {}", format!("thread scaling (d={})", DEPTH).green());
+
+    for threads in [1, 2, 4, 8] {
+        let mut pos = Position::from_fen(fen).unwrap();
+        let mut ctx = engine::Context::new(64);
+        ctx.set_threads(threads);
+
+        let instant = std::time::Instant::now();
+        engine::search(&mut pos, &mut ctx, &engine::Limits::depth(DEPTH), |_, _| {});
+        let elapsed = instant.elapsed().as_secs_f64();
+
+        if threads == 1 {
+            base_time = elapsed;
+        }
+
+        println!(
+            "  * {} threads: {:.0} ms, {} nodes, {:.0} knps, time to depth x{:.2}",
+            threads,
+            elapsed * 1000.0,
+            ctx.nodes(),
+            ctx.nodes() as f64 / elapsed / 1000.0,
+            base_time / elapsed
+        );
+    }
+}
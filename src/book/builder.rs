@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use crate::{
+    book::{ENTRY_SIZE, Entry, polyglot},
+    game::{board::colors, pgn::Game},
+};
+
+/// Which moves of a PGN database go into a book and how much they weigh.
+pub(crate) struct BuildOptions {
+    /// Moves played after this ply are left out.
+    pub(crate) max_ply: usize,
+    /// A move must have been played in at least this many games to be kept.
+    pub(crate) min_games: u32,
+    /// Points a move earns for each game won, drawn or lost by the side that played it.
+    pub(crate) win_points: u32,
+    pub(crate) draw_points: u32,
+    pub(crate) loss_points: u32,
+    /// Keeps only the moves of one color, e.g. to build a repertoire for White.
+    pub(crate) side: Option<usize>,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        Self {
+            max_ply: 40,
+            min_games: 3,
+            win_points: 2,
+            draw_points: 1,
+            loss_points: 0,
+            side: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct MoveStats {
+    games: u32,
+    points: u64,
+}
+
+/// Replays the games and returns the entries of a Polyglot book, sorted by key and then by
+/// decreasing weight. Games without a result are skipped. The weight of a move is the sum of its
+/// points, scaled down when the best move of a position would overflow 16 bits.
+pub(crate) fn build_book(games: &[Game], options: &BuildOptions) -> Vec<u8> {
+    let mut stats = HashMap::<(u64, u16), MoveStats>::new();
+
+    for game in games {
+        let winner = match game.result.as_str() {
+            "1-0" => Some(colors::WHITE),
+            "0-1" => Some(colors::BLACK),
+            "1/2-1/2" => None,
+            _ => continue,
+        };
+        let mut pos = game.start_position();
+
+        for &mv in game.moves.iter().take(options.max_ply) {
+            let color = pos.get_active_color();
+
+            if options.side.is_none_or(|side| side == color) {
+                let points = match winner {
+                    Some(winner) if winner == color => options.win_points,
+                    Some(_) => options.loss_points,
+                    None => options.draw_points,
+                };
+                let move_stats = stats
+                    .entry((polyglot::key(&pos), polyglot::encode_move(mv)))
+                    .or_default();
+
+                move_stats.games += 1;
+                move_stats.points += points as u64;
+            }
+
+            pos.play_move(mv);
+        }
+    }
+
+    let mut moves = stats
+        .into_iter()
+        .filter(|(_, move_stats)| move_stats.games >= options.min_games)
+        .map(|((key, mv), move_stats)| (key, mv, move_stats.points))
+        .collect::<Vec<_>>();
+    moves.sort_by(|a, b| a.0.cmp(&b.0).then(b.2.cmp(&a.2)).then(a.1.cmp(&b.1)));
+
+    let mut bytes = Vec::with_capacity(moves.len() * ENTRY_SIZE);
+
+    for position_moves in moves.chunk_by(|a, b| a.0 == b.0) {
+        // the first move has the most points
+        let max_points = position_moves[0].2.max(u16::MAX as u64);
+
+        for &(key, mv, points) in position_moves {
+            let weight = (points * u16::MAX as u64 / max_points) as u16;
+            bytes.extend(Entry { key, mv, weight }.to_bytes());
+        }
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        book::Book,
+        game::{moves::to_uci, pgn::read_games, position::Position},
+    };
+
+    use super::*;
+
+    const PGN: &str = "1. e4 e5 2. Nf3 1-0\n\n1. e4 c5 0-1\n\n1. d4 d5 1/2-1/2\n\n1. e4 e5 *\n";
+
+    fn book_moves(options: &BuildOptions, uci_moves: &str) -> Vec<(String, u16)> {
+        let games = read_games(PGN)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let book = Book::from_bytes(&build_book(&games, options));
+        let mut pos = Position::from_fen(Position::START_FEN).unwrap();
+
+        for str in uci_moves.split_whitespace() {
+            pos.play_move(pos.parse_uci_move(str).unwrap());
+        }
+
+        book.moves(&pos)
+            .into_iter()
+            .map(|(mv, weight)| (to_uci(mv), weight))
+            .collect()
+    }
+
+    fn options() -> BuildOptions {
+        BuildOptions {
+            min_games: 1,
+            ..BuildOptions::default()
+        }
+    }
+
+    #[test]
+    fn weights_by_result() {
+        assert_eq!(
+            book_moves(&options(), ""),
+            [("e2e4".to_string(), 2), ("d2d4".to_string(), 1)]
+        );
+        assert_eq!(
+            book_moves(&options(), "e2e4"),
+            [("c7c5".to_string(), 2), ("e7e5".to_string(), 0)]
+        );
+
+        let options = BuildOptions {
+            win_points: 1,
+            loss_points: 1,
+            ..options()
+        };
+        assert_eq!(
+            book_moves(&options, "e2e4"),
+            [("c7c5".to_string(), 1), ("e7e5".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn filters() {
+        let min_games = BuildOptions {
+            min_games: 2,
+            ..options()
+        };
+        assert_eq!(book_moves(&min_games, ""), [("e2e4".to_string(), 2)]);
+
+        let max_ply = BuildOptions {
+            max_ply: 1,
+            ..options()
+        };
+        assert_eq!(book_moves(&max_ply, "").len(), 2);
+        assert!(book_moves(&max_ply, "e2e4").is_empty());
+
+        let white_only = BuildOptions {
+            side: Some(colors::WHITE),
+            ..options()
+        };
+        assert!(book_moves(&white_only, "e2e4").is_empty());
+        assert_eq!(book_moves(&white_only, "e2e4 e7e5").len(), 1);
+    }
+
+    #[test]
+    fn scaled_weights() {
+        let games = read_games("1. e4 e5 1-0\n\n1. e4 c5 1-0\n\n1. d4 d5 0-1")
+            .into_iter()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let options = BuildOptions {
+            win_points: 40_000,
+            loss_points: 20_000,
+            ..options()
+        };
+        let bytes = build_book(&games, &options);
+        let book = Book::from_bytes(&bytes);
+        let pos = Position::from_fen(Position::START_FEN).unwrap();
+
+        let weights = book
+            .moves(&pos)
+            .iter()
+            .map(|&(_, weight)| weight)
+            .collect::<Vec<_>>();
+
+        assert_eq!(bytes.len(), 5 * ENTRY_SIZE);
+        assert_eq!(weights, [u16::MAX, u16::MAX / 4]);
+    }
+}
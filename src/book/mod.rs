@@ -0,0 +1,210 @@
+mod builder;
+mod polyglot;
+
+use std::{fs, io};
+
+use rand::random_range;
+
+use crate::game::{moves::Move, position::Position};
+
+/// Key, move, weight and learn data, all big-endian.
+const ENTRY_SIZE: usize = 16;
+
+/// How a move is chosen when a position has several book moves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Selection {
+    /// Always the move with the highest weight.
+    BestWeight,
+    /// Any move, with a probability proportional to its weight.
+    WeightedRandom,
+}
+
+pub(crate) use builder::{BuildOptions, build_book};
+
+#[derive(Clone, Copy)]
+struct Entry {
+    key: u64,
+    mv: u16,
+    weight: u16,
+}
+
+impl Entry {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            mv: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+        }
+    }
+
+    /// The learn data is left at zero.
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.mv.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes
+    }
+}
+
+/// An opening book in Polyglot format, whose entries are sorted by key.
+pub(crate) struct Book {
+    entries: Vec<Entry>,
+}
+
+impl Book {
+    pub(crate) fn open(path: &str) -> io::Result<Self> {
+        Ok(Self::from_bytes(&fs::read(path)?))
+    }
+
+    /// A truncated last entry is ignored.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            entries: bytes
+                .chunks_exact(ENTRY_SIZE)
+                .map(Entry::from_bytes)
+                .collect(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The book moves of a position with their weights, in the order of the book.
+    /// Entries whose move isn't legal in the position are skipped.
+    pub(crate) fn moves(&self, pos: &Position) -> Vec<(Move, u16)> {
+        let key = polyglot::key(pos);
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let legal_moves = pos.legal_moves();
+
+        self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| {
+                legal_moves
+                    .as_slice()
+                    .iter()
+                    .find(|&&mv| polyglot::encode_move(mv) == entry.mv)
+                    .map(|&mv| (mv, entry.weight))
+            })
+            .collect()
+    }
+
+    /// Returns `None` when the position is out of book. Moves with a zero weight are never picked.
+    pub(crate) fn pick(&self, pos: &Position, selection: Selection) -> Option<Move> {
+        let moves = self
+            .moves(pos)
+            .into_iter()
+            .filter(|&(_, weight)| weight > 0)
+            .collect::<Vec<_>>();
+
+        match selection {
+            Selection::BestWeight => moves
+                .iter()
+                .max_by_key(|&&(_, weight)| weight)
+                .map(|&(mv, _)| mv),
+            Selection::WeightedRandom => {
+                let total = moves.iter().map(|&(_, weight)| weight as u64).sum::<u64>();
+
+                if total == 0 {
+                    return None;
+                }
+
+                let mut target = random_range(0..total);
+
+                moves.into_iter().find_map(|(mv, weight)| {
+                    if target < weight as u64 {
+                        return Some(mv);
+                    }
+
+                    target -= weight as u64;
+                    None
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::moves::to_uci;
+
+    use super::*;
+
+    fn entry(pos: &Position, uci: &str, weight: u16) -> Vec<u8> {
+        let mv = match uci {
+            "" => 0,
+            _ => polyglot::encode_move(pos.parse_uci_move(uci).unwrap()),
+        };
+        let entry = Entry {
+            key: polyglot::key(pos),
+            mv,
+            weight,
+        };
+        entry.to_bytes().to_vec()
+    }
+
+    fn start_book() -> Book {
+        let pos = Position::from_fen(Position::START_FEN).unwrap();
+        let after_d4 =
+            Position::from_fen("rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1")
+                .unwrap();
+        let mut entries = [
+            entry(&pos, "e2e4", 3),
+            entry(&pos, "d2d4", 1),
+            entry(&pos, "g1f3", 0),
+            // not a legal move
+            entry(&pos, "", 5),
+            entry(&after_d4, "d7d5", 1),
+            entry(&after_d4, "g8f6", 1),
+        ];
+        // sorting is stable so the moves of a position keep their order
+        entries.sort_by_key(|entry| entry[..8].to_vec());
+
+        let mut bytes = entries.concat();
+        bytes.extend([0; 7]);
+        Book::from_bytes(&bytes)
+    }
+
+    #[test]
+    fn book_moves() {
+        let book = start_book();
+        let pos = Position::from_fen(Position::START_FEN).unwrap();
+        let moves = book
+            .moves(&pos)
+            .iter()
+            .map(|&(mv, weight)| (to_uci(mv), weight))
+            .collect::<Vec<_>>();
+
+        assert_eq!(book.len(), 6);
+        assert_eq!(
+            moves,
+            [
+                ("e2e4".to_string(), 3),
+                ("d2d4".to_string(), 1),
+                ("g1f3".to_string(), 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn selection() {
+        let book = start_book();
+        let mut pos = Position::from_fen(Position::START_FEN).unwrap();
+        let e4 = pos.parse_uci_move("e2e4").unwrap();
+        let d4 = pos.parse_uci_move("d2d4").unwrap();
+
+        assert_eq!(book.pick(&pos, Selection::BestWeight), Some(e4));
+
+        let picks = (0..200)
+            .map(|_| book.pick(&pos, Selection::WeightedRandom).unwrap())
+            .collect::<Vec<_>>();
+        assert!(picks.iter().all(|&mv| mv == e4 || mv == d4));
+        assert!(picks.contains(&e4) && picks.contains(&d4));
+
+        pos.play_move(e4);
+        assert_eq!(book.pick(&pos, Selection::BestWeight), None);
+        assert_eq!(book.pick(&pos, Selection::WeightedRandom), None);
+    }
+}
@@ -0,0 +1,299 @@
+use std::{fs, path::Path};
+
+use crate::{
+    benchmarks,
+    book::{self, Book, BuildOptions},
+    engine::{self, Context, Limits, Variation},
+    game::{
+        board::colors,
+        moves::{Move, san, to_uci},
+        pgn,
+        position::Position,
+    },
+    macros::bench,
+    retrograde::DtmTables,
+    test_suite,
+};
+
+const USAGE: &str = "\
+Usage:
+  chess                          speak UCI or CECP over stdin/stdout
+  chess perft <depth> [fen]      count the leaf nodes at a given depth
+  chess divide <depth> [fen]     same, split between the legal moves
+  chess pgn <file>               replay the games of a PGN file
+  chess book <file> [fen]        list the moves of a Polyglot book
+  chess makebook <pgn> <bin> [max-ply <n>] [min-games <n>] [points <win> <draw> <loss>]
+                 [side white|black]
+                                 build a Polyglot book from the games of a PGN file
+  chess epd <file> [depth <n> | movetime <ms>]
+                                 run a test suite, at depth 6 by default
+  chess mate <moves> [fen]       list every key move mating within a number of moves,
+                                 with its solution tree
+  chess bench                    measure move generation and search speed
+  chess retro <dir> <material>...
+                                 generate distance-to-mate tables, e.g. KRK or KRKP,
+                                 in a directory also holding those already generated";
+
+/// Runs a command given on the command line.
+pub(crate) fn run(args: &[String]) {
+    let result = match args[0].as_str() {
+        "perft" => perft(&args[1..], false),
+        "divide" => perft(&args[1..], true),
+        "pgn" => replay_pgn(&args[1..]),
+        "book" => list_book_moves(&args[1..]),
+        "makebook" => make_book(&args[1..]),
+        "epd" => run_test_suite(&args[1..]),
+        "retro" => generate_tables(&args[1..]),
+        "mate" => solve_mate(&args[1..]),
+        "bench" => {
+            benchmarks::run();
+            Ok(())
+        }
+        _ => Err(format!("unknown command: {}", args[0])),
+    };
+
+    if let Err(message) = result {
+        eprintln!("{}\n\n{}", message, USAGE);
+    }
+}
+
+fn perft(args: &[String], divide: bool) -> Result<(), String> {
+    let depth = match args.first().map(|arg| arg.parse::<usize>()) {
+        Some(Ok(depth)) => depth,
+        _ => return Err("expected a depth".to_string()),
+    };
+    let fen = match args.len() {
+        1 => Position::START_FEN.to_string(),
+        _ => args[1..].join(" "),
+    };
+    let mut pos = Position::from_fen(&fen).map_err(|error| format!("invalid FEN: {:?}", error))?;
+
+    bench!({
+        let nodes = match divide {
+            true => {
+                let divided = pos.divide(depth);
+
+                for &(mv, nodes) in &divided {
+                    println!("{}: {}", to_uci(mv), nodes);
+                }
+
+                divided.iter().map(|&(_, nodes)| nodes).sum()
+            }
+            false => pos.perft(depth),
+        };
+
+        println!("\nNodes searched: {}", nodes);
+    });
+
+    Ok(())
+}
+
+/// Prints the final position of every game, or the reason it couldn't be replayed.
+fn replay_pgn(args: &[String]) -> Result<(), String> {
+    let path = args.first().ok_or("expected a file")?;
+    let pgn = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+
+    for (i, game) in pgn::read_games(&pgn).iter().enumerate() {
+        match game {
+            Ok(game) => {
+                let mut pos = game.start_position();
+
+                for &mv in &game.moves {
+                    pos.play_move(mv);
+                }
+
+                println!("Game {}: {} {}", i + 1, game.result, pos.to_fen());
+            }
+            Err(error) => println!("Game {}: {}", i + 1, error),
+        };
+    }
+
+    Ok(())
+}
+
+/// Prints the book moves of a position, the initial one by default, with their weights.
+fn list_book_moves(args: &[String]) -> Result<(), String> {
+    let path = args.first().ok_or("expected a file")?;
+    let book = Book::open(path).map_err(|error| format!("{}: {}", path, error))?;
+    let fen = match args.len() {
+        1 => Position::START_FEN.to_string(),
+        _ => args[1..].join(" "),
+    };
+    let mut pos = Position::from_fen(&fen).map_err(|error| format!("invalid FEN: {:?}", error))?;
+    let moves = book.moves(&pos);
+
+    if moves.is_empty() {
+        println!("Out of book ({} entries)", book.len());
+    }
+
+    for (mv, weight) in moves {
+        println!("{} {}", san::to_san(&mut pos, mv), weight);
+    }
+
+    Ok(())
+}
+
+/// Writes a book from the games of a PGN file, skipping those that cannot be replayed.
+fn make_book(args: &[String]) -> Result<(), String> {
+    let (Some(pgn_path), Some(bin_path)) = (args.first(), args.get(1)) else {
+        return Err("expected a PGN file and a book file".to_string());
+    };
+    let options = parse_book_options(&args[2..])?;
+    let pgn = fs::read_to_string(pgn_path).map_err(|error| format!("{}: {}", pgn_path, error))?;
+    let games = pgn::read_games(&pgn)
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+    let bytes = book::build_book(&games, &options);
+
+    fs::write(bin_path, &bytes).map_err(|error| format!("{}: {}", bin_path, error))?;
+    println!(
+        "{} games, {} entries",
+        games.len(),
+        Book::from_bytes(&bytes).len()
+    );
+    Ok(())
+}
+
+fn parse_book_options(args: &[String]) -> Result<BuildOptions, String> {
+    let mut options = BuildOptions::default();
+    let mut args = args.iter().map(String::as_str);
+    let number = |name: &str, arg: Option<&str>| {
+        arg.and_then(|arg| arg.parse::<u32>().ok())
+            .ok_or(format!("expected a number after {}", name))
+    };
+
+    while let Some(name) = args.next() {
+        match name {
+            "max-ply" => options.max_ply = number(name, args.next())? as usize,
+            "min-games" => options.min_games = number(name, args.next())?,
+            "points" => {
+                options.win_points = number(name, args.next())?;
+                options.draw_points = number(name, args.next())?;
+                options.loss_points = number(name, args.next())?;
+            }
+            "side" => {
+                options.side = match args.next() {
+                    Some("white") => Some(colors::WHITE),
+                    Some("black") => Some(colors::BLACK),
+                    _ => return Err("expected white or black after side".to_string()),
+                }
+            }
+            _ => return Err(format!("unknown option: {}", name)),
+        };
+    }
+
+    Ok(options)
+}
+
+fn run_test_suite(args: &[String]) -> Result<(), String> {
+    let path = args.first().ok_or("expected a file")?;
+    let epd_file = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    let value = args.get(2).map(|arg| arg.parse::<u64>());
+
+    let limits = match (args.get(1).map(String::as_str), value) {
+        (None, _) => Limits::depth(6),
+        (Some("depth"), Some(Ok(depth))) => Limits::depth(depth as usize),
+        (Some("movetime"), Some(Ok(move_time))) => Limits {
+            move_time: Some(move_time),
+            ..Limits::depth(engine::MAX_DEPTH)
+        },
+        _ => return Err("expected depth <n> or movetime <ms>".to_string()),
+    };
+
+    test_suite::run(&epd_file, &limits);
+    Ok(())
+}
+
+/// Prints the key moves of a mate in N problem, then the solution tree of each.
+fn solve_mate(args: &[String]) -> Result<(), String> {
+    let moves = match args.first().map(|arg| arg.parse::<usize>()) {
+        Some(Ok(moves)) if moves > 0 => moves,
+        _ => return Err("expected a number of moves".to_string()),
+    };
+    let fen = match args.len() {
+        1 => Position::START_FEN.to_string(),
+        _ => args[1..].join(" "),
+    };
+    let mut pos = Position::from_fen(&fen).map_err(|error| format!("invalid FEN: {:?}", error))?;
+    let mut ctx = Context::new(1);
+    let key_moves = engine::solve_mate(&mut pos, &mut ctx, moves);
+
+    if key_moves.is_empty() {
+        println!("No mate in {}", moves);
+        return Ok(());
+    }
+
+    let names = key_moves
+        .iter()
+        .map(|variation| san::to_san(&mut pos, variation.mv))
+        .collect::<Vec<String>>();
+    let status = match key_moves.len() {
+        1 => "sound",
+        _ => "cooked",
+    };
+    println!("Key moves: {} ({})", names.join(", "), status);
+
+    for variation in &key_moves {
+        println!();
+        print_solution_tree(&mut pos, variation, 0);
+    }
+
+    Ok(())
+}
+
+/// One row per move, each defence indented below the attacker move it answers.
+fn print_solution_tree(pos: &mut Position, variation: &Variation, indent: usize) {
+    let undo_info = pos.undo_info();
+    println!("{}{}", "  ".repeat(indent), numbered_san(pos, variation.mv));
+    pos.play_move(variation.mv);
+
+    for defence in &variation.defences {
+        let defence_undo_info = pos.undo_info();
+        println!(
+            "{}{}",
+            "  ".repeat(indent + 1),
+            numbered_san(pos, defence.mv)
+        );
+        pos.play_move(defence.mv);
+
+        for continuation in &defence.continuations {
+            print_solution_tree(pos, continuation, indent + 2);
+        }
+
+        pos.undo_move(defence.mv, defence_undo_info);
+    }
+
+    pos.undo_move(variation.mv, undo_info);
+}
+
+/// `12.Nf3` or `12...Nf6`.
+fn numbered_san(pos: &mut Position, mv: Move) -> String {
+    let dots = match pos.get_active_color() {
+        colors::WHITE => ".",
+        _ => "...",
+    };
+    format!("{}{}{}", pos.full_move_number(), dots, san::to_san(pos, mv))
+}
+
+/// Generates the tables missing from a directory and prints what each of them holds.
+fn generate_tables(args: &[String]) -> Result<(), String> {
+    let dir = Path::new(args.first().ok_or("expected a directory")?);
+    let mut tables = DtmTables::open(dir).map_err(|error| format!("{:?}: {}", dir, error))?;
+
+    for name in &args[1..] {
+        let name = tables
+            .generate(name)
+            .ok_or(format!("invalid material: {}", name))?;
+        let summary = tables.summary(&name).unwrap();
+
+        println!(
+            "{}: {} wins, {} draws, {} losses, longest mate in {} plies",
+            name, summary.wins, summary.draws, summary.losses, summary.longest_mate
+        );
+    }
+
+    tables
+        .save(dir)
+        .map_err(|error| format!("{:?}: {}", dir, error))
+}
@@ -0,0 +1,195 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use crate::{
+    engine::{
+        killer_moves, pv,
+        time_management::{Limits, TimeManager},
+        transposition as tp,
+    },
+    game::moves::Move,
+    syzygy::Tablebases,
+};
+
+/// How many nodes are searched between two reads of the stop flag.
+const STOP_CHECK_INTERVAL: u64 = 2048;
+
+/// The tables and counters shared by every node of a search.
+pub(crate) struct Context {
+    /// Shared with the helper threads.
+    pub(super) tt: Arc<tp::Table>,
+    pub(super) kmt: killer_moves::Table,
+    pub(super) time: TimeManager,
+    pub(super) tablebases: Option<Arc<Tablebases>>,
+    /// The only moves searched at the root when the position is in the tablebases.
+    pub(super) root_moves: Option<Vec<Move>>,
+    /// The number of threads searching, the main one included.
+    pub(super) threads: usize,
+    /// The number of best lines searched.
+    pub(super) multi_pv: usize,
+    /// The first moves of the lines already found in this iteration, skipped at the root.
+    pub(super) excluded_moves: Vec<Move>,
+    pub(super) pvt: pv::Table,
+    /// Whether the move of the transposition table is searched first, only turned off to measure it.
+    pub(super) tt_move_ordering: bool,
+    nodes: u64,
+    /// Counted by every thread, by batches of `STOP_CHECK_INTERVAL`.
+    shared_nodes: Arc<AtomicU64>,
+    tb_hits: u64,
+    /// Counted by the helper threads as they happen.
+    helper_tb_hits: Arc<AtomicU64>,
+    is_helper: bool,
+    stop: Arc<AtomicBool>,
+    /// Raised while pondering, lowered on `ponderhit`.
+    ponder: Arc<AtomicBool>,
+    stopped: bool,
+}
+
+impl Context {
+    pub(crate) fn new(hash_size_mb: usize) -> Self {
+        Self {
+            tt: Arc::new(tp::create_table(hash_size_mb)),
+            kmt: killer_moves::create_table(),
+            time: TimeManager::unlimited(),
+            tablebases: None,
+            root_moves: None,
+            threads: 1,
+            multi_pv: 1,
+            excluded_moves: Vec::new(),
+            pvt: pv::create_table(),
+            tt_move_ordering: true,
+            nodes: 0,
+            shared_nodes: Arc::new(AtomicU64::new(0)),
+            tb_hits: 0,
+            helper_tb_hits: Arc::new(AtomicU64::new(0)),
+            is_helper: false,
+            stop: Arc::new(AtomicBool::new(false)),
+            ponder: Arc::new(AtomicBool::new(false)),
+            stopped: false,
+        }
+    }
+
+    /// A context for a helper thread, sharing the transposition table.
+    /// The helper searches until `stop` is raised or the node limit is reached.
+    pub(super) fn helper(&self, stop: &Arc<AtomicBool>) -> Self {
+        Self {
+            tt: Arc::clone(&self.tt),
+            kmt: killer_moves::create_table(),
+            time: self.time.for_helper(),
+            tablebases: self.tablebases.clone(),
+            root_moves: self.root_moves.clone(),
+            threads: 1,
+            multi_pv: 1,
+            excluded_moves: Vec::new(),
+            pvt: pv::create_table(),
+            tt_move_ordering: self.tt_move_ordering,
+            nodes: 0,
+            shared_nodes: Arc::clone(&self.shared_nodes),
+            tb_hits: 0,
+            helper_tb_hits: Arc::clone(&self.helper_tb_hits),
+            is_helper: true,
+            stop: Arc::clone(stop),
+            ponder: Arc::clone(&self.ponder),
+            stopped: false,
+        }
+    }
+
+    /// Forgets what the earlier searches learnt, keeping the allocated table.
+    pub(crate) fn clear(&mut self) {
+        tp::clear_table(&self.tt);
+        self.kmt = killer_moves::create_table();
+    }
+
+    /// The flag other threads can raise to abort the search.
+    pub(crate) fn stop_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.stop)
+    }
+
+    /// The flag to lower on `ponderhit`, raised by a search started with `ponder` set.
+    pub(crate) fn ponder_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.ponder)
+    }
+
+    pub(crate) fn set_tablebases(&mut self, tablebases: Option<Arc<Tablebases>>) {
+        self.tablebases = tablebases;
+    }
+
+    pub(crate) fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub(crate) fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.multi_pv = multi_pv.max(1);
+    }
+
+    pub(crate) fn set_tt_move_ordering(&mut self, enabled: bool) {
+        self.tt_move_ordering = enabled;
+    }
+
+    pub(super) fn start_search(&mut self, limits: &Limits) {
+        self.time = TimeManager::new(limits);
+        self.tb_hits = 0;
+        self.nodes = 0;
+        self.shared_nodes.store(0, Ordering::Relaxed);
+        self.helper_tb_hits.store(0, Ordering::Relaxed);
+        self.stopped = false;
+    }
+
+    /// The nodes searched by every thread, up to the last batch of the others.
+    pub(crate) fn nodes(&self) -> u64 {
+        self.shared_nodes.load(Ordering::Relaxed) + self.nodes % STOP_CHECK_INTERVAL
+    }
+
+    /// Counts the nodes searched since the last batch.
+    pub(super) fn end_helper_search(&self) {
+        self.shared_nodes
+            .fetch_add(self.nodes % STOP_CHECK_INTERVAL, Ordering::Relaxed);
+    }
+
+    /// The tablebase hits of every thread.
+    pub(crate) fn tb_hits(&self) -> u64 {
+        self.tb_hits + self.helper_tb_hits.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn count_tb_hit(&mut self) {
+        if self.is_helper {
+            self.helper_tb_hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.tb_hits += 1;
+        }
+    }
+
+    pub(crate) const fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Counts a new node and returns whether the search should be aborted.
+    pub(super) fn visit_node(&mut self) -> bool {
+        self.nodes += 1;
+
+        // the helpers only see the count of the others by batches
+        if !self.is_helper && self.time.is_node_limit_reached(self.nodes()) {
+            self.stopped = true;
+        }
+
+        if self.nodes.is_multiple_of(STOP_CHECK_INTERVAL) {
+            self.shared_nodes
+                .fetch_add(STOP_CHECK_INTERVAL, Ordering::Relaxed);
+
+            if self.time.is_pondering() && !self.ponder.load(Ordering::Relaxed) {
+                self.time.on_ponderhit();
+            }
+
+            if self.stop.load(Ordering::Relaxed)
+                || self.time.is_hard_limit_reached()
+                || self.time.is_node_limit_reached(self.nodes())
+            {
+                self.stopped = true;
+            }
+        }
+
+        self.stopped
+    }
+}
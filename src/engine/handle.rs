@@ -0,0 +1,169 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    engine::{Context, Iteration, Limits, score::Score},
+    game::{
+        moves::{Move, NULL_MOVE},
+        position::Position,
+    },
+};
+
+/// The result of the last completed iteration of a running search.
+#[derive(Clone)]
+pub(crate) struct Progress {
+    pub(crate) depth: usize,
+    pub(crate) score: Score,
+    pub(crate) best_mv: Move,
+    pub(crate) pv: Vec<Move>,
+}
+
+/// A search running on a worker thread, which owns the position and the context while it runs.
+pub(crate) struct SearchHandle {
+    stop: Arc<AtomicBool>,
+    ponder: Arc<AtomicBool>,
+    progress: Arc<Mutex<Progress>>,
+    thread: JoinHandle<(Move, Context)>,
+}
+
+impl SearchHandle {
+    /// Starts searching `pos` on a new thread.
+    /// `report` is called after every completed iteration and `on_finish` with the best move
+    /// once the search is over, whether it completed or was stopped.
+    pub(crate) fn spawn(
+        mut pos: Position,
+        mut ctx: Context,
+        limits: Limits,
+        mut report: impl FnMut(&mut Position, &Iteration) + Send + 'static,
+        on_finish: impl FnOnce(Move) + Send + 'static,
+    ) -> Self {
+        let stop = ctx.stop_flag();
+        stop.store(false, Ordering::Relaxed);
+        // raised before the thread starts so that an early `ponderhit` isn't lost
+        let ponder = ctx.ponder_flag();
+        ponder.store(limits.ponder, Ordering::Relaxed);
+
+        let progress = Arc::new(Mutex::new(Progress {
+            depth: 0,
+            score: 0,
+            best_mv: NULL_MOVE,
+            pv: Vec::new(),
+        }));
+        let thread_progress = Arc::clone(&progress);
+
+        let thread = thread::spawn(move || {
+            let best_mv = super::search(&mut pos, &mut ctx, &limits, |pos, iteration| {
+                let line = iteration.best_line();
+                *thread_progress.lock().unwrap() = Progress {
+                    depth: iteration.depth,
+                    score: line.score,
+                    best_mv: line.pv.first().copied().unwrap_or(NULL_MOVE),
+                    pv: line.pv.clone(),
+                };
+                report(pos, iteration);
+            });
+
+            on_finish(best_mv);
+            (best_mv, ctx)
+        });
+
+        Self {
+            stop,
+            ponder,
+            progress,
+            thread,
+        }
+    }
+
+    /// Asks the search to stop as soon as possible. Doesn't wait for it.
+    pub(crate) fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Switches a search started with `ponder` set to its time limits, counted from now.
+    pub(crate) fn ponderhit(&self) {
+        self.ponder.store(false, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    pub(crate) fn progress(&self) -> Progress {
+        self.progress.lock().unwrap().clone()
+    }
+
+    /// The best move found so far, `NULL_MOVE` until the first iteration is complete.
+    pub(crate) fn best_move(&self) -> Move {
+        self.progress.lock().unwrap().best_mv
+    }
+
+    pub(crate) fn pv(&self) -> Vec<Move> {
+        self.progress.lock().unwrap().pv.clone()
+    }
+
+    /// Waits for the search to end and gives back its best move and its context,
+    /// whose transposition table can be reused by the next search.
+    pub(crate) fn join(self) -> (Move, Context) {
+        self.thread.join().unwrap()
+    }
+
+    /// Stops the search and waits for it.
+    pub(crate) fn stop_and_join(self) -> (Move, Context) {
+        self.stop();
+        self.join()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use crate::engine::MAX_DEPTH;
+
+    use super::*;
+
+    #[test]
+    fn stop_running_search() {
+        let pos = Position::from_fen(Position::START_FEN).unwrap();
+        let legal_moves = pos.legal_moves();
+        let handle = SearchHandle::spawn(
+            pos.clone(),
+            Context::new(1),
+            Limits::depth(MAX_DEPTH),
+            |_, _| {},
+            |_| {},
+        );
+
+        while handle.best_move() == NULL_MOVE {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(!handle.is_finished());
+        assert!(!handle.pv().is_empty());
+
+        let progress = handle.progress();
+        let (best_mv, ctx) = handle.stop_and_join();
+
+        assert!(legal_moves.contains(best_mv));
+        assert!(progress.depth >= 1);
+        assert!(ctx.nodes() > 0);
+    }
+
+    #[test]
+    fn reuse_context() {
+        let pos = Position::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let search =
+            |ctx| SearchHandle::spawn(pos.clone(), ctx, Limits::depth(3), |_, _| {}, |_| {}).join();
+
+        let (best_mv1, ctx) = search(Context::new(1));
+        let (best_mv2, _) = search(ctx);
+
+        assert_eq!(best_mv1, best_mv2);
+    }
+}
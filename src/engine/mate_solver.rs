@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+
+use crate::{
+    engine::{
+        Iteration, Line,
+        context::Context,
+        score::{MATE_SCORE, Score},
+    },
+    game::{
+        moves::{Move, MoveList, NULL_MOVE, encoding},
+        position::Position,
+    },
+};
+
+/// Whether the attacker, to move, mates within a number of moves.
+type Cache = HashMap<(u64, usize), bool>;
+
+/// An attacker move forcing mate, with every defence against it.
+pub(crate) struct Variation {
+    pub(crate) mv: Move,
+    /// Empty when the move mates.
+    pub(crate) defences: Vec<Defence>,
+}
+
+/// A defender move, with every attacker move still mating in time after it.
+pub(crate) struct Defence {
+    pub(crate) mv: Move,
+    /// More than one is a dual.
+    pub(crate) continuations: Vec<Variation>,
+}
+
+impl Variation {
+    /// The plies until mate when the defender holds out as long as possible and the attacker
+    /// mates as soon as possible.
+    pub(crate) fn mate_length(&self) -> usize {
+        let defence_length = |defence: &Defence| {
+            let continuation = defence
+                .continuations
+                .iter()
+                .map(Variation::mate_length)
+                .min();
+            1 + continuation.unwrap_or(0)
+        };
+
+        1 + self.defences.iter().map(defence_length).max().unwrap_or(0)
+    }
+
+    /// The longest defence followed by the shortest mate against it, down to the mate.
+    pub(crate) fn main_line(&self) -> Vec<Move> {
+        let mut line = vec![self.mv];
+        let longest_defence = self
+            .defences
+            .iter()
+            .max_by_key(|defence| defence.shortest_continuation().map(Variation::mate_length));
+
+        if let Some(defence) = longest_defence {
+            line.push(defence.mv);
+
+            if let Some(continuation) = defence.shortest_continuation() {
+                line.extend(continuation.main_line());
+            }
+        }
+
+        line
+    }
+}
+
+impl Defence {
+    fn shortest_continuation(&self) -> Option<&Variation> {
+        self.continuations
+            .iter()
+            .min_by_key(|variation| variation.mate_length())
+    }
+}
+
+/// Every key move mating within `moves` moves, with its solution tree. More than one key move
+/// is a cook. The result is incomplete if the search was stopped.
+pub(crate) fn solve(pos: &mut Position, ctx: &mut Context, moves: usize) -> Vec<Variation> {
+    solutions(pos, ctx, &mut Cache::new(), moves)
+}
+
+/// Answers `go mate`: reports one line per key move, the shortest mate first, and returns the
+/// first key move, or `NULL_MOVE` when there is no mate in time.
+pub(super) fn search(
+    pos: &mut Position,
+    ctx: &mut Context,
+    moves: usize,
+    mut report: impl FnMut(&mut Position, &Iteration),
+) -> Move {
+    let mut key_moves = solve(pos, ctx, moves);
+
+    if ctx.is_stopped() || key_moves.is_empty() {
+        return NULL_MOVE;
+    }
+
+    key_moves.sort_by_cached_key(Variation::mate_length);
+    let lines = key_moves
+        .iter()
+        .map(|variation| Line {
+            score: MATE_SCORE - variation.mate_length() as Score,
+            pv: variation.main_line(),
+        })
+        .collect();
+    let iteration = Iteration {
+        depth: 2 * moves - 1,
+        lines,
+        nodes: ctx.nodes(),
+        tb_hits: 0,
+        elapsed: ctx.time.elapsed(),
+    };
+    report(pos, &iteration);
+
+    key_moves[0].mv
+}
+
+fn solutions(
+    pos: &mut Position,
+    ctx: &mut Context,
+    cache: &mut Cache,
+    moves: usize,
+) -> Vec<Variation> {
+    let mut variations = Vec::new();
+
+    for &mv in &attacker_moves(pos, moves) {
+        if mates_after(pos, ctx, cache, mv, moves) {
+            let defences = defences(pos, ctx, cache, mv, moves);
+            variations.push(Variation { mv, defences });
+        }
+    }
+
+    variations
+}
+
+fn defences(
+    pos: &mut Position,
+    ctx: &mut Context,
+    cache: &mut Cache,
+    mv: Move,
+    moves: usize,
+) -> Vec<Defence> {
+    let undo_info = pos.undo_info();
+    pos.play_move(mv);
+    let mut defences = Vec::new();
+
+    for &reply in &pos.legal_moves() {
+        let reply_undo_info = pos.undo_info();
+        pos.play_move(reply);
+        let continuations = solutions(pos, ctx, cache, moves - 1);
+        pos.undo_move(reply, reply_undo_info);
+        defences.push(Defence {
+            mv: reply,
+            continuations,
+        });
+    }
+
+    pos.undo_move(mv, undo_info);
+    defences
+}
+
+/// Whether the side to move mates within `moves` moves whatever the defence.
+fn mates_within(pos: &mut Position, ctx: &mut Context, cache: &mut Cache, moves: usize) -> bool {
+    if moves == 0 || ctx.visit_node() {
+        return false;
+    }
+
+    let key = (pos.hash(), moves);
+
+    if let Some(&is_mate) = cache.get(&key) {
+        return is_mate;
+    }
+
+    let mut is_mate = false;
+
+    for &mv in &attacker_moves(pos, moves) {
+        if mates_after(pos, ctx, cache, mv, moves) {
+            is_mate = true;
+            break;
+        }
+    }
+
+    if !ctx.is_stopped() {
+        cache.insert(key, is_mate);
+    }
+
+    is_mate
+}
+
+/// Whether the attacker's move mates at once or leaves every defence mated in the moves left.
+/// The defender tries all of its moves, stopping at the first refutation.
+fn mates_after(
+    pos: &mut Position,
+    ctx: &mut Context,
+    cache: &mut Cache,
+    mv: Move,
+    moves: usize,
+) -> bool {
+    let undo_info = pos.undo_info();
+    pos.play_move(mv);
+    let replies = pos.legal_moves();
+
+    let is_mate = match replies.is_empty() {
+        true => pos.is_check(),
+        false => {
+            let mut is_refuted = moves == 1;
+
+            for &reply in &replies {
+                if is_refuted {
+                    break;
+                }
+
+                let reply_undo_info = pos.undo_info();
+                pos.play_move(reply);
+                is_refuted = !mates_within(pos, ctx, cache, moves - 1);
+                pos.undo_move(reply, reply_undo_info);
+            }
+
+            !is_refuted
+        }
+    };
+
+    pos.undo_move(mv, undo_info);
+    is_mate
+}
+
+/// On the last move, only checks can mate. Earlier, checks are tried first as they leave the
+/// defender the fewest replies.
+fn attacker_moves(pos: &Position, moves: usize) -> MoveList {
+    let mut list = pos.legal_moves();
+
+    if moves == 1 {
+        list.retain(encoding::gives_check);
+    }
+
+    list.as_mut_slice()
+        .sort_by_key(|&mv| !encoding::gives_check(mv));
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{engine::Limits, game::moves::to_uci};
+
+    use super::*;
+
+    fn key_moves(fen: &str, moves: usize) -> Vec<Variation> {
+        let mut pos = Position::from_fen(fen).unwrap();
+        solve(&mut pos, &mut Context::new(1), moves)
+    }
+
+    fn uci_moves(moves: &[Move]) -> Vec<String> {
+        moves.iter().map(|&mv| to_uci(mv)).collect()
+    }
+
+    #[test]
+    fn mate_in_one() {
+        let variations = key_moves("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 1);
+
+        assert_eq!(variations.len(), 1);
+        assert_eq!(to_uci(variations[0].mv), "a1a8");
+        assert!(variations[0].defences.is_empty());
+        assert_eq!(variations[0].mate_length(), 1);
+
+        assert!(key_moves("k7/8/2K5/8/8/8/8/7R w - - 0 1", 1).is_empty());
+    }
+
+    #[test]
+    fn cooks_and_solution_tree() {
+        let variations = key_moves("k7/8/2K5/8/8/8/8/7R w - - 0 1", 2);
+        let keys: Vec<Move> = variations.iter().map(|variation| variation.mv).collect();
+
+        assert_eq!(uci_moves(&keys), ["c6b6", "c6c7"]);
+        assert_eq!(
+            uci_moves(&variations[0].main_line()),
+            ["c6b6", "a8b8", "h1h8"]
+        );
+        assert_eq!(variations[0].mate_length(), 3);
+
+        // every defence is answered, here by a single mate
+        let defences = &variations[1].defences;
+        assert_eq!(defences.len(), 1);
+        assert_eq!(uci_moves(&[defences[0].mv]), ["a8a7"]);
+        assert_eq!(defences[0].continuations.len(), 1);
+    }
+
+    #[test]
+    fn longest_defence() {
+        let fen = "r1b3kr/ppp1Bp1p/1b6/n2P4/2p3q1/2Q2N2/P4PPP/RN2R1K1 w - - 1 1";
+        let variations = key_moves(fen, 3);
+
+        assert_eq!(variations.len(), 1);
+        assert_eq!(variations[0].mate_length(), 5);
+        assert_eq!(variations[0].main_line().len(), 5);
+        assert!(key_moves(fen, 2).is_empty());
+    }
+
+    #[test]
+    fn go_mate() {
+        let mut pos = Position::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        let mut ctx = Context::new(1);
+        let limits = Limits {
+            mate: Some(2),
+            ..Limits::depth(1)
+        };
+        let mut scores = Vec::new();
+
+        let best_mv = crate::engine::search(&mut pos, &mut ctx, &limits, |_, iteration| {
+            scores = iteration.lines.iter().map(|line| line.score).collect()
+        });
+
+        assert_eq!(to_uci(best_mv), "c6b6");
+        assert_eq!(scores, [MATE_SCORE - 3, MATE_SCORE - 3]);
+    }
+}
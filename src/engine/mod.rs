@@ -1,15 +1,30 @@
+mod context;
+mod handle;
 mod killer_moves;
+mod mate_solver;
 mod move_ordering;
 mod null_move_pruning;
 mod pv;
 mod quiescence;
 mod score;
 mod static_eval;
+mod tablebases;
+mod time_management;
 mod transposition;
 
+use std::{
+    cmp::Reverse,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
 use crate::{
     game::{
-        moves::{Move, MoveList, NULL_MOVE},
+        moves::{Move, MoveList, NULL_MOVE, encoding},
         position::Position,
     },
     macros::ternary,
@@ -20,63 +35,225 @@ use score::*;
 use static_eval::eval_position;
 use transposition as tp;
 
-const MAX_DEPTH: usize = 255;
+pub(crate) use context::Context;
+pub(crate) use handle::SearchHandle;
+pub(crate) use mate_solver::{Variation, solve as solve_mate};
+pub(crate) use score::{MATE_SCORE, Score, is_mate_score, moves_to_mate, to_centipawns};
+pub(crate) use static_eval::piece_value;
+pub(crate) use time_management::Limits;
+pub(crate) use transposition::DEFAULT_SIZE_MB as DEFAULT_HASH_SIZE_MB;
+
+pub(crate) const MAX_DEPTH: usize = 255;
 const DELTA: Score = 500;
 
-pub(crate) fn run(pos: &mut Position, max_depth: usize, print_pv: bool) {
-    let mut tt = tp::create_table();
-    let mut kmt = killer_moves::create_table();
-    let mut prev_score = 0;
+/// The outcome of a completed iteration of the iterative deepening.
+pub(crate) struct Iteration {
+    pub(crate) depth: usize,
+    /// One line per PV searched, the best first.
+    pub(crate) lines: Vec<Line>,
+    pub(crate) nodes: u64,
+    pub(crate) tb_hits: u64,
+    pub(crate) elapsed: Duration,
+}
+
+impl Iteration {
+    pub(crate) fn best_line(&self) -> &Line {
+        &self.lines[0]
+    }
+}
+
+/// A principal variation and its score.
+#[derive(Clone)]
+pub(crate) struct Line {
+    pub(crate) score: Score,
+    pub(crate) pv: Vec<Move>,
+}
+
+/// Searches with a fresh context, printing the PV of every iteration if `print_pv` is set.
+/// Returns the best move and the score of the last completed iteration.
+pub(crate) fn run(pos: &mut Position, limits: &Limits, print_pv: bool) -> (Move, Score) {
+    run_with_context(
+        pos,
+        &mut Context::new(DEFAULT_HASH_SIZE_MB),
+        limits,
+        print_pv,
+    )
+}
+
+/// Like `run`, with a context that can be reused between searches.
+pub(crate) fn run_with_context(
+    pos: &mut Position,
+    ctx: &mut Context,
+    limits: &Limits,
+    print_pv: bool,
+) -> (Move, Score) {
+    let mut score = 0;
+
+    let best_mv = search(pos, ctx, limits, |pos, iteration| {
+        score = iteration.best_line().score;
+
+        if print_pv {
+            for line in pv::stringify_lines(pos, &iteration.lines) {
+                println!("{} {}", iteration.depth, line);
+            }
+        }
+    });
+
+    (best_mv, score)
+}
+
+/// Deepens the search until one of the `limits` is reached or the search is stopped,
+/// calling `report` after every completed iteration.
+/// Returns the best move of the last completed iteration.
+///
+/// With more than one thread, the helpers search copies of the position until the main thread
+/// is done, filling the shared transposition table (Lazy SMP).
+pub(crate) fn search(
+    pos: &mut Position,
+    ctx: &mut Context,
+    limits: &Limits,
+    report: impl FnMut(&mut Position, &Iteration),
+) -> Move {
+    ctx.start_search(limits);
+
+    if let Some(moves) = limits.mate {
+        return mate_solver::search(pos, ctx, moves, report);
+    }
+
+    ctx.root_moves = tablebases::rank_root_moves(pos, ctx);
+
+    let max_depth = limits.depth.min(MAX_DEPTH - 1);
+    let helpers_stop = Arc::new(AtomicBool::new(false));
+    let helpers: Vec<Context> = (1..ctx.threads)
+        .map(|_| ctx.helper(&helpers_stop))
+        .collect();
+
+    thread::scope(|scope| {
+        for (index, mut helper_ctx) in helpers.into_iter().enumerate() {
+            let mut helper_pos = pos.clone();
+            scope.spawn(move || search_helper(&mut helper_pos, &mut helper_ctx, index, max_depth));
+        }
+
+        let best_mv = search_main(pos, ctx, max_depth, report);
+        helpers_stop.store(true, Ordering::Relaxed);
+        best_mv
+    })
+}
+
+fn search_main(
+    pos: &mut Position,
+    ctx: &mut Context,
+    max_depth: usize,
+    mut report: impl FnMut(&mut Position, &Iteration),
+) -> Move {
+    let mut best_mv = NULL_MOVE;
+    let mut prev_lines = Vec::<Line>::new();
     let mut delta = 250;
+    let root_move_count = match &ctx.root_moves {
+        Some(root_moves) => root_moves.len(),
+        None => pos.legal_moves().len(),
+    };
+    let line_count = ctx.multi_pv.min(root_move_count).max(1);
 
-    for depth in 1..=max_depth {
-        let score = ternary!(
-            depth <= 4,
-            analyze(pos, &mut tt, &mut kmt, 0, depth, -MATE_SCORE, MATE_SCORE),
-            analyze_aspiration_windows(
-                pos,
-                &mut tt,
-                &mut kmt,
-                depth,
-                prev_score - delta,
-                prev_score + delta
-            )
-        );
-        prev_score = score;
+    'deepening: for depth in 1..=max_depth {
+        let mut lines = Vec::<Line>::with_capacity(line_count);
+        ctx.excluded_moves.clear();
+
+        // every line is searched without the first moves of the lines above it
+        for line_index in 0..line_count {
+            let score = match prev_lines.get(line_index) {
+                Some(prev_line) if depth > 4 => {
+                    let (alpha, beta) = (prev_line.score - delta, prev_line.score + delta);
+                    analyze_aspiration_windows(pos, ctx, depth, alpha, beta)
+                }
+                _ => analyze(pos, ctx, 0, depth, -MATE_SCORE, MATE_SCORE),
+            };
+
+            if ctx.is_stopped() {
+                break 'deepening;
+            }
+
+            let pv = pv::line(&ctx.pvt, 0).to_vec();
+
+            if let Some(&mv) = pv.first() {
+                ctx.excluded_moves.push(mv);
+            }
+
+            lines.push(Line { score, pv });
+        }
 
         if depth % 4 == 0 {
             delta += 250;
         }
 
-        if print_pv {
-            println!(
-                "{} {} {}",
-                depth,
-                stringify_score(score),
-                pv::stringify(pos, &tt, depth)
-            );
+        lines.sort_by_key(|line| Reverse(line.score));
+        let prev_best_mv = best_mv;
+
+        if let Some(&mv) = lines[0].pv.first() {
+            best_mv = mv;
+        }
+
+        prev_lines = lines.clone();
+        let iteration = Iteration {
+            depth,
+            lines,
+            nodes: ctx.nodes(),
+            tb_hits: ctx.tb_hits(),
+            elapsed: ctx.time.elapsed(),
+        };
+        report(pos, &iteration);
+
+        ctx.time.on_iteration(depth > 1 && best_mv != prev_best_mv);
+
+        if ctx.time.is_soft_limit_reached() {
+            break;
+        }
+    }
+
+    if best_mv == NULL_MOVE {
+        // stopped before the first iteration could complete
+        let moves = pos.legal_moves();
+
+        if let Some(&mv) = ctx.root_moves.as_ref().and_then(|moves| moves.first()) {
+            best_mv = mv;
+        } else if !moves.is_empty() {
+            best_mv = moves[0];
+        }
+    }
+
+    best_mv
+}
+
+/// Deepens the search with a full window until the main thread is done. Half of the helpers
+/// start one ply deeper so that the threads don't all search the same depth at the same time.
+fn search_helper(pos: &mut Position, ctx: &mut Context, index: usize, max_depth: usize) {
+    for depth in 1 + index % 2..=max_depth {
+        analyze(pos, ctx, 0, depth, -MATE_SCORE, MATE_SCORE);
+
+        if ctx.is_stopped() {
+            break;
         }
     }
+
+    ctx.end_helper_search();
 }
 
 fn analyze(
     pos: &mut Position,
-    tt: &mut tp::Table,
-    kmt: &mut killer_moves::Table,
+    ctx: &mut Context,
     ply: usize,
     depth: usize,
     alpha: Score,
     beta: Score,
 ) -> Score {
-    let score = negamax(pos, tt, kmt, ply, depth, alpha, beta);
+    let score = negamax(pos, ctx, ply, depth, alpha, beta);
     pos.reset_reps();
     score
 }
 
 fn analyze_aspiration_windows(
     pos: &mut Position,
-    tt: &mut tp::Table,
-    kmt: &mut killer_moves::Table,
+    ctx: &mut Context,
     depth: usize,
     mut alpha: Score,
     mut beta: Score,
@@ -84,7 +261,11 @@ fn analyze_aspiration_windows(
     let mut score: Score = 0;
 
     for _ in 0..2 {
-        score = analyze(pos, tt, kmt, 0, depth, alpha, beta);
+        score = analyze(pos, ctx, 0, depth, alpha, beta);
+
+        if ctx.is_stopped() {
+            break;
+        }
 
         if score <= alpha {
             alpha = -MATE_SCORE;
@@ -93,54 +274,85 @@ fn analyze_aspiration_windows(
         } else {
             break;
         }
+
+        ctx.time.on_aspiration_fail();
     }
 
     score
 }
 
 macro_rules! set_exact {
-    ($tt: expr, $hash: expr, $depth: expr, $score: expr) => {
-        let entry = tp::Entry::exact($hash, $score, $depth, NULL_MOVE);
-        tp::set_entry($tt, entry);
-        return entry.score;
+    ($tt: expr, $hash: expr, $depth: expr, $ply: expr, $score: expr) => {
+        let score = $score;
+        tp::set_entry(
+            $tt,
+            tp::Entry::exact($hash, score_to_tt(score, $ply), $depth, NULL_MOVE),
+        );
+        return score;
     };
 }
 
 fn negamax(
     pos: &mut Position,
-    tt: &mut tp::Table,
-    kmt: &mut killer_moves::Table,
+    ctx: &mut Context,
     ply: usize,
     depth: usize,
     mut alpha: Score,
     mut beta: Score,
 ) -> Score {
+    pv::clear(&mut ctx.pvt, ply);
+
+    if ctx.visit_node() {
+        return 0;
+    }
+
     let old_alpha = alpha;
     let hash = pos.hash();
+    // cutting a PV node short would truncate the PV
+    let is_pv_node = beta - alpha > 1;
 
-    if let Some(score) = tp::cached_score(tt, hash, depth, ply, &mut alpha, &mut beta) {
+    // the cached move may not be one of the root moves kept by the tablebases or by MultiPV
+    let is_restricted_root =
+        ply == 0 && (ctx.root_moves.is_some() || !ctx.excluded_moves.is_empty());
+
+    if !is_restricted_root
+        && !is_pv_node
+        && let Some(score) = tp::cached_score(&ctx.tt, hash, depth, ply, &mut alpha, &mut beta)
+    {
         return score;
     }
 
     if pos.half_move_clock() >= 50 || pos.rep_count() >= 2 || pos.piece_count() == 2 {
-        set_exact!(tt, hash, depth, DRAW_SCORE);
+        set_exact!(&ctx.tt, hash, depth, ply, DRAW_SCORE);
+    }
+
+    if ply > 0
+        && let Some(score) = tablebases::probe(pos, ctx)
+    {
+        set_exact!(&ctx.tt, hash, depth, ply, score);
     }
 
     let mut moves = pos.legal_moves();
 
     if moves.is_empty() {
-        let score = ternary!(pos.is_check(), -MATE_SCORE, DRAW_SCORE);
-        set_exact!(tt, hash, depth, score_to_tt(score, ply));
+        // the closer the mate, the better for the winning side
+        let score = ternary!(pos.is_check(), -MATE_SCORE + ply as Score, DRAW_SCORE);
+        set_exact!(&ctx.tt, hash, depth, ply, score);
     }
 
     if depth == 0 {
-        set_exact!(
-            tt,
-            hash,
-            depth,
-            // quiescence::quiesce(pos, alpha, beta, Some(moves))
-            eval_position(pos)
+        let score = quiescence::quiesce(pos, ctx, ply, alpha, beta, Some(moves), true);
+
+        if ctx.is_stopped() {
+            return 0;
+        }
+
+        let flag = tp::flags::get_flag(old_alpha, beta, score);
+        tp::set_entry(
+            &ctx.tt,
+            tp::Entry::new(flag, hash, score_to_tt(score, ply), depth, NULL_MOVE),
         );
+        return score;
     }
 
     if depth == 1 {
@@ -151,18 +363,31 @@ fn negamax(
         }
     }
 
-    if let Some(score) = prune_null_move(pos, tt, kmt, ply, depth, beta) {
+    if !is_restricted_root && let Some(score) = prune_null_move(pos, ctx, ply, depth, beta) {
         return score;
     }
 
-    move_ordering::sort_moves(&mut moves, &kmt[depth]);
-    negamax_moves(pos, tt, kmt, ply, depth, &moves, old_alpha, alpha, beta)
+    if is_restricted_root && let Some(root_moves) = &ctx.root_moves {
+        moves.retain(|mv| root_moves.contains(&mv));
+    }
+
+    if ply == 0 {
+        moves.retain(|mv| !ctx.excluded_moves.contains(&mv));
+    }
+
+    // the best move of an earlier search, unless written by another position with the same hash
+    let tt_mv = tp::get_entry(&ctx.tt, hash)
+        .map(|entry| entry.mv)
+        .filter(|&mv| ctx.tt_move_ordering && pos.is_legal(mv))
+        .unwrap_or(NULL_MOVE);
+
+    move_ordering::sort_moves(pos, &mut moves, &ctx.kmt[depth], tt_mv);
+    negamax_moves(pos, ctx, ply, depth, &moves, old_alpha, alpha, beta)
 }
 
 fn negamax_moves(
     pos: &mut Position,
-    tt: &mut tp::Table,
-    kmt: &mut killer_moves::Table,
+    ctx: &mut Context,
     ply: usize,
     depth: usize,
     moves: &MoveList,
@@ -193,10 +418,24 @@ fn negamax_moves(
             break;
         }
 
+        // neither can a capture whose exchange doesn't win enough
+        if best_mv != NULL_MOVE
+            && can_futility_prune
+            && encoding::is_capture(mv)
+            && !encoding::gives_check(mv)
+            && !pos.see_ge(mv, alpha - static_score - FUTILITY_MARGIN + 1)
+        {
+            continue;
+        }
+
         pos.play_move(mv);
-        let mv_score = move_score(pos, tt, kmt, ply, depth, alpha, beta, mv);
+        let mv_score = move_score(pos, ctx, ply, depth, alpha, beta, mv);
         pos.undo_move(mv, undo_info);
 
+        if ctx.is_stopped() {
+            return 0;
+        }
+
         if mv_score <= best_score {
             continue;
         }
@@ -205,24 +444,30 @@ fn negamax_moves(
         best_mv = mv;
 
         if move_ordering::is_quiet_move(mv) {
-            killer_moves::update(kmt, mv, depth);
+            killer_moves::update(&mut ctx.kmt, mv, depth);
         }
 
         if best_score > alpha {
             alpha = best_score;
+            pv::update(&mut ctx.pvt, ply, mv);
         }
     }
 
     let flag = tp::flags::get_flag(old_alpha, beta, best_score);
-    let entry = tp::Entry::new(flag, pos.hash(), best_score, depth, best_mv);
-    tp::set_entry(tt, entry);
+    let entry = tp::Entry::new(
+        flag,
+        pos.hash(),
+        score_to_tt(best_score, ply),
+        depth,
+        best_mv,
+    );
+    tp::set_entry(&ctx.tt, entry);
     best_score
 }
 
 fn move_score(
     pos: &mut Position,
-    tt: &mut tp::Table,
-    kmt: &mut killer_moves::Table,
+    ctx: &mut Context,
     ply: usize,
     depth: usize,
     alpha: Score,
@@ -230,12 +475,152 @@ fn move_score(
     mv: Move,
 ) -> Score {
     if depth >= 2 && move_ordering::is_quiet_move(mv) {
-        let mv_score = -negamax(pos, tt, kmt, ply + 1, depth - 2, -alpha - 1, -alpha);
+        let mv_score = -negamax(pos, ctx, ply + 1, depth - 2, -alpha - 1, -alpha);
 
         if mv_score <= alpha {
             return mv_score;
         }
     }
 
-    -negamax(pos, tt, kmt, ply + 1, depth - 1, -beta, -alpha)
+    -negamax(pos, ctx, ply + 1, depth - 1, -beta, -alpha)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::moves::to_uci;
+
+    use super::*;
+
+    fn best_move(fen: &str, depth: usize) -> String {
+        let mut pos = Position::from_fen(fen).unwrap();
+        to_uci(run(&mut pos, &Limits::depth(depth), false).0)
+    }
+
+    #[test]
+    fn no_horizon_blunders() {
+        // the pawn is defended
+        assert_ne!(best_move("4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1", 1), "e1e5");
+        // the queen would be taken back
+        assert_ne!(best_move("4k3/8/8/8/8/2p5/1p6/QK6 w - - 0 1", 1), "a1b2");
+        // the queen hangs
+        assert_eq!(best_move("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", 1), "d1d5");
+    }
+
+    #[test]
+    fn mate_in_n() {
+        let search_mate = |fen: &str, depth: usize| {
+            let mut pos = Position::from_fen(fen).unwrap();
+            let (mv, score) = run(&mut pos, &Limits::depth(depth), false);
+            (to_uci(mv), moves_to_mate(score))
+        };
+
+        // back rank
+        assert_eq!(
+            search_mate("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3),
+            ("a1a8".to_string(), 1)
+        );
+        // the king takes the opposition first
+        assert_eq!(
+            search_mate("k7/8/2K5/8/8/8/8/7R w - - 0 1", 4),
+            ("c6b6".to_string(), 2)
+        );
+        // whatever Black plays
+        assert_eq!(search_mate("k7/8/1K6/8/8/8/8/7R b - - 0 1", 3).1, -1);
+    }
+
+    #[test]
+    fn pv_length_matches_depth() {
+        let fens = [
+            Position::START_FEN,
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1BBPPP/R2QK2R w KQ - 0 8",
+        ];
+
+        for fen in fens {
+            let mut pos = Position::from_fen(fen).unwrap();
+            let mut ctx = Context::new(1);
+
+            search(&mut pos, &mut ctx, &Limits::depth(4), |pos, iteration| {
+                let pv = &iteration.best_line().pv;
+                assert_eq!(pv.len(), iteration.depth);
+
+                let undo_infos: Vec<u32> = pv
+                    .iter()
+                    .map(|&mv| {
+                        assert!(pos.is_legal(mv));
+                        let undo_info = pos.undo_info();
+                        pos.play_move(mv);
+                        undo_info
+                    })
+                    .collect();
+
+                for (&mv, &undo_info) in pv.iter().zip(&undo_infos).rev() {
+                    pos.undo_move(mv, undo_info);
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn multi_pv() {
+        let mut pos = Position::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let mut ctx = Context::new(1);
+        let mut lines = Vec::new();
+        ctx.set_multi_pv(3);
+
+        search(&mut pos, &mut ctx, &Limits::depth(3), |_, iteration| {
+            lines = iteration.lines.clone()
+        });
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(to_uci(lines[0].pv[0]), "d1d5");
+        assert!(lines.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        assert!(lines.iter().all(|line| line.pv.len() == 3));
+
+        let first_moves: Vec<Move> = lines.iter().map(|line| line.pv[0]).collect();
+        assert!(!first_moves[1..].contains(&first_moves[0]));
+        assert_ne!(first_moves[1], first_moves[2]);
+
+        // no more lines than legal moves
+        let mut pos = Position::from_fen("7k/8/8/8/8/8/6q1/7K w - - 0 1").unwrap();
+        ctx.set_multi_pv(3);
+        search(&mut pos, &mut ctx, &Limits::depth(2), |_, iteration| {
+            lines = iteration.lines.clone()
+        });
+        assert_eq!(lines.len(), 1);
+    }
+
+    #[test]
+    fn lazy_smp() {
+        let fen = "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1BBPPP/R2QK2R w KQ - 0 8";
+        let mut pos = Position::from_fen(fen).unwrap();
+        let mut ctx = Context::new(1);
+        let mut depths = Vec::new();
+        ctx.set_threads(2);
+
+        let best_mv = search(&mut pos, &mut ctx, &Limits::depth(3), |_, iteration| {
+            depths.push(iteration.depth)
+        });
+
+        assert!(pos.legal_moves().contains(best_mv));
+        assert_eq!(depths, [1, 2, 3]);
+        assert_eq!(pos.to_fen(), fen);
+    }
+
+    #[test]
+    fn node_limit_across_threads() {
+        let fen = "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1BBPPP/R2QK2R w KQ - 0 8";
+        let mut pos = Position::from_fen(fen).unwrap();
+        let mut ctx = Context::new(1);
+        let limits = Limits {
+            nodes: Some(20_000),
+            ..Limits::depth(MAX_DEPTH)
+        };
+        ctx.set_threads(4);
+
+        search(&mut pos, &mut ctx, &limits, |_, _| {});
+
+        // every thread may overshoot by the batch the others haven't seen yet
+        assert!(ctx.nodes() >= 20_000);
+        assert!(ctx.nodes() <= 20_000 + 4 * 2048);
+    }
 }
@@ -1,12 +1,34 @@
+use std::cmp::Reverse;
+
 use crate::{
     engine::{score::Score, static_eval::piece_value},
-    game::moves::{Move, MoveList, encoding},
+    game::{
+        moves::{Move, MoveList, encoding},
+        position::Position,
+    },
 };
 
-pub(crate) fn sort_moves(moves: &mut MoveList, km: &super::killer_moves::Pair) {
-    moves
-        .as_mut_slice()
-        .sort_by(|&a, &b| move_score(b, km).cmp(&move_score(a, km)));
+/// The move of the transposition table comes first. Captures that don't lose material come
+/// before the killer moves, the others after them.
+pub(crate) fn sort_moves(
+    pos: &Position,
+    moves: &mut MoveList,
+    km: &super::killer_moves::Pair,
+    tt_mv: Move,
+) {
+    moves.as_mut_slice().sort_by_cached_key(|&mv| {
+        if mv == tt_mv {
+            return Reverse(Score::MAX);
+        }
+
+        let mut score = move_score(mv, km);
+
+        if encoding::is_capture(mv) && pos.see_ge(mv, 0) {
+            score += 600_000;
+        }
+
+        Reverse(score)
+    });
 }
 
 pub(crate) fn sort_captures(moves: &mut MoveList) {
@@ -16,7 +38,7 @@ pub(crate) fn sort_captures(moves: &mut MoveList) {
 }
 
 /// A quiet move is not a check, capture or promotion.
-/// Quiet moves appear last in a sorted move list.
+/// Quiet moves appear last in a sorted move list, unless one of them is the table move.
 pub(crate) const fn is_quiet_move(mv: Move) -> bool {
     !encoding::gives_check(mv) && !encoding::is_capture(mv) && !encoding::is_promotion(mv)
 }
@@ -53,7 +75,7 @@ mod tests {
     fn sort_moves() {
         let pos = Position::from_fen("r3k3/1P6/8/4pP2/8/2Q5/8/4K2R w K e6 0 1").unwrap();
         let mut moves = pos.legal_moves();
-        super::sort_moves(&mut moves, &(NULL_MOVE, NULL_MOVE));
+        super::sort_moves(&pos, &mut moves, &(NULL_MOVE, NULL_MOVE), NULL_MOVE);
 
         for i in 0..(moves.len() - 1) {
             let mv1 = moves[i];
@@ -68,4 +90,34 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn losing_captures_after_killers() {
+        let pos = Position::from_fen("4k3/8/2p5/3p4/8/8/8/n2QK3 w - - 0 1").unwrap();
+        let mut moves = pos.legal_moves();
+        let killer = pos.parse_uci_move("e1f2").unwrap();
+        super::sort_moves(&pos, &mut moves, &(killer, NULL_MOVE), NULL_MOVE);
+
+        let index_of = |uci| {
+            let mv = pos.parse_uci_move(uci).unwrap();
+            moves
+                .as_slice()
+                .iter()
+                .position(|&other| other == mv)
+                .unwrap()
+        };
+
+        assert!(index_of("d1a1") < index_of("e1f2"));
+        assert!(index_of("e1f2") < index_of("d1d5"));
+    }
+
+    #[test]
+    fn table_move_first() {
+        let pos = Position::from_fen("4k3/8/2p5/3p4/8/8/8/n2QK3 w - - 0 1").unwrap();
+        let mut moves = pos.legal_moves();
+        let tt_mv = pos.parse_uci_move("e1f1").unwrap();
+        super::sort_moves(&pos, &mut moves, &(NULL_MOVE, NULL_MOVE), tt_mv);
+
+        assert_eq!(moves[0], tt_mv);
+    }
 }
@@ -1,5 +1,5 @@
 use crate::{
-    engine::{score::Score, transposition as tp},
+    engine::{context::Context, score::Score},
     game::position::Position,
     macros::ternary,
 };
@@ -17,8 +17,7 @@ const fn can_prune_null_move(pos: &Position, depth: usize) -> bool {
 
 pub(super) fn prune_null_move(
     pos: &mut Position,
-    tt: &mut tp::Table,
-    kmt: &mut super::killer_moves::Table,
+    ctx: &mut Context,
     ply: usize,
     depth: usize,
     beta: Score,
@@ -29,7 +28,7 @@ pub(super) fn prune_null_move(
 
     let ep_sq = pos.get_ep_square();
     pos.play_null_move();
-    let score = -super::negamax(pos, tt, kmt, ply + 1, depth - REDUCTION, -beta, -beta + 1);
+    let score = -super::negamax(pos, ctx, ply + 1, depth - REDUCTION, -beta, -beta + 1);
     pos.undo_null_move(ep_sq);
 
     ternary!(score >= beta, Some(score), None)
@@ -1,118 +1,136 @@
 use crate::{
-    engine::transposition as tp,
+    engine::{Line, MAX_DEPTH, score::stringify_score},
     game::{
-        board::{colors, lines, pieces, squares, wings},
-        moves::{Move, NULL_MOVE, castling::get_wing, encoding},
+        board::colors,
+        moves::{Move, NULL_MOVE, san},
         position::Position,
     },
     macros::ternary,
 };
 
-type PVNode = (u16, u32);
-type PV = Vec<PVNode>;
-
-pub(super) fn stringify(pos: &mut Position, tt: &tp::Table, depth: usize) -> String {
-    let pv = collect_pv(pos, tt, depth);
+pub(super) fn stringify(pos: &mut Position, pv: &[Move]) -> String {
     let mut color = pos.get_active_color();
-    let first_mv_black = color == colors::BLACK;
+    let mut mv_number = pos.full_move_number();
     let mut output = String::new();
+    let mut undo_infos = Vec::<u32>::new();
+
+    if color == colors::BLACK && !pv.is_empty() {
+        let string = format!("{}...", mv_number);
+        output.push_str(&string);
+    }
 
-    for &(mv_number, mv) in &pv {
+    for &mv in pv {
         if color == colors::WHITE {
             let string = format!("{}.", mv_number);
             output.push_str(&string);
         }
 
-        output.push_str(&stringify_move(mv));
+        output.push_str(&san::to_san(pos, mv));
         output.push(' ');
+        undo_infos.push(pos.undo_info());
+        pos.play_move(mv);
         color = colors::rev(color);
-    }
-
-    if first_mv_black {
-        let string = format!("{}...", pv[0].0);
-        output.insert_str(0, &string);
-    }
 
-    output
-}
-
-fn collect_pv(pos: &mut Position, tt: &tp::Table, depth: usize) -> PV {
-    let mut pv = PV::new();
-    let mut undo_infos = Vec::<u32>::new();
-    let mut mv_number = 1;
-
-    for _ in 0..depth {
-        if pos.legal_moves().is_empty() {
-            break;
+        if color == colors::WHITE {
+            mv_number += 1;
         }
-
-        match tp::get_entry(tt, pos.hash()) {
-            Some(entry) => {
-                if entry.mv == NULL_MOVE {
-                    break;
-                }
-
-                pv.push((mv_number, entry.mv));
-                undo_infos.push(pos.undo_info());
-                pos.play_move(entry.mv);
-
-                if pos.get_active_color() == colors::WHITE {
-                    mv_number += 1;
-                }
-            }
-            None => {
-                break;
-            }
-        };
     }
 
     for i in (0..pv.len()).rev() {
-        pos.undo_move(pv[i].1, undo_infos[i]);
+        pos.undo_move(pv[i], undo_infos[i]);
     }
 
-    pv
+    output
 }
 
-fn stringify_move(mv: Move) -> String {
-    let src_sq = encoding::src_square(mv);
-    let dest_sq = encoding::dest_square(mv);
+/// One string per line, in the same order: the score, then the moves.
+pub(super) fn stringify_lines(pos: &mut Position, lines: &[Line]) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| {
+            format!(
+                "{} {}",
+                stringify_score(line.score),
+                stringify(pos, &line.pv)
+            )
+        })
+        .collect()
+}
 
-    if encoding::is_castling(mv) {
-        let result = ternary!(
-            get_wing(src_sq, dest_sq) == wings::QUEEN_SIDE,
-            "0-0-0",
-            "0-0"
-        );
-        return result.to_string();
+/// The triangular PV table: the line of a ply is the best move found there, followed by the
+/// line of the next ply. Only the first `lengths[ply]` moves of a line are set.
+pub(super) struct Table {
+    lines: Box<[[Move; MAX_DEPTH]]>,
+    lengths: [usize; MAX_DEPTH],
+}
+
+pub(super) fn create_table() -> Table {
+    Table {
+        lines: vec![[NULL_MOVE; MAX_DEPTH]; MAX_DEPTH].into_boxed_slice(),
+        lengths: [0; MAX_DEPTH],
     }
+}
 
-    let src_piece = encoding::src_piece(mv);
-    let is_capture = encoding::is_capture(mv);
-    let mut result = String::new();
+/// Empties the line of a node about to be searched.
+pub(super) const fn clear(pvt: &mut Table, ply: usize) {
+    pvt.lengths[ply] = 0;
+}
 
-    if !pieces::is_pawn(src_piece) {
-        result.push(pieces::initial_of(src_piece).to_ascii_uppercase());
+/// Makes `mv` followed by the line of the next ply the line of `ply`.
+pub(super) fn update(pvt: &mut Table, ply: usize, mv: Move) {
+    let child_len = ternary!(ply + 1 < MAX_DEPTH, pvt.lengths[ply + 1], 0);
+    let (parents, children) = pvt.lines.split_at_mut(ply + 1);
 
-        if is_capture {
-            result.push('x');
-        }
+    parents[ply][0] = mv;
+    parents[ply][1..=child_len].copy_from_slice(&children[0][..child_len]);
+    pvt.lengths[ply] = child_len + 1;
+}
 
-        result.push_str(&squares::name_of(dest_sq));
-        return result;
-    }
+pub(super) fn line(pvt: &Table, ply: usize) -> &[Move] {
+    &pvt.lines[ply][..pvt.lengths[ply]]
+}
 
-    if is_capture {
-        let src_file = squares::file_of(src_sq);
-        result.push(lines::file_name(src_file));
-        result.push('x');
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_lines() {
+        let pos = Position::from_fen(Position::START_FEN).unwrap();
+        let [e4, d4, nf3] = ["e2e4", "d2d4", "g1f3"].map(|str| pos.parse_uci_move(str).unwrap());
+        let mut pvt = create_table();
+
+        clear(&mut pvt, 2);
+        update(&mut pvt, 2, nf3);
+        update(&mut pvt, 1, d4);
+        update(&mut pvt, 0, e4);
+        assert_eq!(line(&pvt, 0), [e4, d4, nf3]);
+
+        // the lines of the nodes cut short are empty
+        clear(&mut pvt, 1);
+        update(&mut pvt, 0, e4);
+        assert_eq!(line(&pvt, 0), [e4]);
     }
 
-    result.push_str(&squares::name_of(dest_sq));
-
-    if encoding::is_promotion(mv) {
-        let promoted = encoding::promoted(mv);
-        result.push(pieces::initial_of(promoted).to_ascii_uppercase());
+    #[test]
+    fn stringify_several_lines() {
+        let mut pos = Position::from_fen(Position::START_FEN).unwrap();
+        let e4 = pos.parse_uci_move("e2e4").unwrap();
+        let d4 = pos.parse_uci_move("d2d4").unwrap();
+        let lines = [
+            Line {
+                score: 300,
+                pv: vec![e4],
+            },
+            Line {
+                score: 0,
+                pv: vec![d4],
+            },
+        ];
+
+        assert_eq!(
+            stringify_lines(&mut pos, &lines),
+            ["+0.30 1.e4 ", "0 1.d4 "]
+        );
     }
-
-    result
 }
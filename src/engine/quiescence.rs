@@ -1,18 +1,45 @@
 use crate::{
-    engine::{score::Score, static_eval::eval_position},
+    engine::{
+        DELTA,
+        context::Context,
+        score::{MATE_SCORE, Score},
+        static_eval::{eval_position, piece_value},
+    },
     game::{
-        moves::{MoveList, encoding},
+        board::pieces::{self, piece_types},
+        moves::{Move, MoveList, encoding},
         position::Position,
     },
+    macros::ternary,
 };
 
-pub(crate) fn quiesce(
+/// Searches captures and queen promotions until the position is quiet, so that the static
+/// evaluation isn't trusted while pieces hang. `moves` are the legal moves of the position if
+/// the caller already has them.
+///
+/// At the first ply, a side in check must play one of its evasions instead of standing pat.
+pub(super) fn quiesce(
     pos: &mut Position,
+    ctx: &mut Context,
+    ply: usize,
     mut alpha: Score,
     beta: Score,
     moves: Option<MoveList>,
+    is_first_ply: bool,
 ) -> Score {
-    let mut best_score = eval_position(pos);
+    if ctx.visit_node() {
+        return 0;
+    }
+
+    let mut moves = moves.unwrap_or_else(|| pos.legal_moves());
+    let is_evading = is_first_ply && pos.is_check();
+
+    if moves.is_empty() {
+        return ternary!(pos.is_check(), -MATE_SCORE + ply as Score, 0);
+    }
+
+    let stand_pat = eval_position(pos);
+    let mut best_score = ternary!(is_evading, Score::MIN, stand_pat);
 
     if best_score >= beta {
         return best_score;
@@ -22,17 +49,22 @@ pub(crate) fn quiesce(
         alpha = best_score;
     }
 
-    let undo_info = pos.undo_info();
-    let mut moves = moves.unwrap_or_else(|| pos.legal_moves());
+    if !is_evading {
+        moves.retain(|mv| is_worth_searching(pos, mv, stand_pat, alpha));
+    }
 
-    moves.retain(encoding::is_capture);
     super::move_ordering::sort_captures(&mut moves);
+    let undo_info = pos.undo_info();
 
     for &mv in &moves {
         pos.play_move(mv);
-        let mv_score = -quiesce(pos, -beta, -alpha, None);
+        let mv_score = -quiesce(pos, ctx, ply + 1, -beta, -alpha, None, false);
         pos.undo_move(mv, undo_info);
 
+        if ctx.is_stopped() {
+            return 0;
+        }
+
         if mv_score >= beta {
             return mv_score;
         }
@@ -48,3 +80,45 @@ pub(crate) fn quiesce(
 
     best_score
 }
+
+/// Captures and queen promotions that may raise alpha and don't lose material.
+fn is_worth_searching(pos: &Position, mv: Move, stand_pat: Score, alpha: Score) -> bool {
+    let is_promotion = encoding::is_promotion(mv);
+
+    if is_promotion && pieces::type_of(encoding::promoted(mv)) != piece_types::QUEEN {
+        return false;
+    }
+
+    if !encoding::is_capture(mv) && !is_promotion {
+        return false;
+    }
+
+    // delta pruning: even winning the piece by a wide margin wouldn't be enough
+    if !is_promotion && stand_pat + piece_value(encoding::captured(mv)) + DELTA <= alpha {
+        return false;
+    }
+
+    pos.see_ge(mv, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stand_pat_and_captures() {
+        let mut ctx = Context::new(1);
+        let mut pos = Position::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let eval = eval_position(&pos);
+        let score = quiesce(&mut pos, &mut ctx, 0, -MATE_SCORE, MATE_SCORE, None, true);
+
+        // the rook takes the queen
+        assert!(score > eval + 5000);
+
+        let mut pos = Position::from_fen("4k3/8/4p3/3p4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let eval = eval_position(&pos);
+        let score = quiesce(&mut pos, &mut ctx, 0, -MATE_SCORE, MATE_SCORE, None, true);
+
+        assert_eq!(score, eval);
+    }
+}
@@ -4,17 +4,23 @@ pub(crate) type Score = i32;
 
 pub(crate) const MATE_SCORE: Score = 1_000_000;
 pub(crate) const DRAW_SCORE: Score = 0;
+/// A tablebase win, below every mate score.
+pub(crate) const TB_WIN_SCORE: Score = MATE_SCORE - 2 * MAX_DEPTH as Score;
+
+/// Mate scores are `MATE_SCORE` minus the number of plies from the root to the mate.
+const MATE_BOUND: Score = MATE_SCORE - MAX_DEPTH as Score;
 
 pub(crate) const fn is_mate_score(score: Score) -> bool {
-    score.abs() >= MATE_SCORE - MAX_DEPTH as Score
+    score >= MATE_BOUND || score <= -MATE_BOUND
 }
 
+/// The table stores the distance to mate from the node instead of from the root.
 pub(crate) const fn score_to_tt(score: Score, ply: usize) -> Score {
-    if score > MATE_SCORE {
+    if score >= MATE_BOUND {
         return score + ply as Score;
     }
 
-    if score < -MATE_SCORE {
+    if score <= -MATE_BOUND {
         return score - ply as Score;
     }
 
@@ -22,29 +28,65 @@ pub(crate) const fn score_to_tt(score: Score, ply: usize) -> Score {
 }
 
 pub(crate) const fn score_from_tt(score: Score, ply: usize) -> Score {
-    if score > MATE_SCORE {
+    if score >= MATE_BOUND {
         return score - ply as Score;
     }
 
-    if score < -MATE_SCORE {
+    if score <= -MATE_BOUND {
         return score + ply as Score;
     }
 
     score
 }
 
+/// The number of moves until mate, negative when the side to move is getting mated.
+pub(crate) const fn moves_to_mate(score: Score) -> Score {
+    let moves = (MATE_SCORE - score.abs() + 1) / 2;
+    ternary!(score > 0, moves, -moves)
+}
+
+/// Scores are computed in thousandths of a pawn.
+pub(crate) const fn to_centipawns(score: Score) -> Score {
+    score / 10
+}
+
+/// `#N` when mating in N moves, `-#N` when getting mated, pawns otherwise.
 pub(super) fn stringify_score(score: Score) -> String {
     if score == 0 {
         return "0".to_string();
     }
 
+    if is_mate_score(score) {
+        let moves = moves_to_mate(score);
+        return format!("{}#{}", ternary!(moves > 0, "", "-"), moves.abs());
+    }
+
     let sign = ternary!(score > 0, '+', '-');
     let abs_score = score.abs() as f32;
 
-    // if is_mate_score(score) {
-    //     let dist_to_mate = ((MATE_SCORE as f32 - abs_score) / 2.0).ceil();
-    //     return format!("{}M{}", sign, dist_to_mate as u8);
-    // }
-
     format!("{}{:.2}", sign, abs_score / 1000.0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mate_scores() {
+        // mating in 2 moves, found 3 plies below the root
+        let score = MATE_SCORE - 6;
+        assert_eq!(score_from_tt(score_to_tt(score, 3), 3), score);
+        assert_eq!(score_to_tt(score, 3), MATE_SCORE - 3);
+        assert_eq!(score_from_tt(MATE_SCORE - 3, 1), MATE_SCORE - 4);
+        assert_eq!(score_to_tt(-score, 3), -MATE_SCORE + 3);
+        assert_eq!(score_to_tt(TB_WIN_SCORE, 3), TB_WIN_SCORE);
+
+        assert_eq!(moves_to_mate(MATE_SCORE - 1), 1);
+        assert_eq!(moves_to_mate(MATE_SCORE - 3), 2);
+        assert_eq!(moves_to_mate(-MATE_SCORE + 2), -1);
+
+        assert_eq!(stringify_score(MATE_SCORE - 3), "#2");
+        assert_eq!(stringify_score(-MATE_SCORE + 4), "-#2");
+        assert_eq!(stringify_score(-1500), "-1.50");
+    }
+}
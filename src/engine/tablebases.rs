@@ -0,0 +1,41 @@
+use crate::{
+    engine::{
+        context::Context,
+        score::{DRAW_SCORE, Score, TB_WIN_SCORE},
+    },
+    game::{moves::Move, position::Position},
+    syzygy::wdl,
+};
+
+/// The root moves that keep the best tablebase result, or `None` when the position isn't covered.
+pub(super) fn rank_root_moves(pos: &mut Position, ctx: &Context) -> Option<Vec<Move>> {
+    let ranks = ctx.tablebases.as_ref()?.rank_root_moves(pos)?;
+    let best_rank = ranks.iter().map(|&(_, rank)| rank).max()?;
+
+    Some(
+        ranks
+            .into_iter()
+            .filter(|&(_, rank)| rank == best_rank)
+            .map(|(mv, _)| mv)
+            .collect(),
+    )
+}
+
+/// Scores a position from the WDL tables right after a capture or a pawn move, where the result
+/// is exact. Cursed wins and blessed losses are draws.
+pub(super) fn probe(pos: &mut Position, ctx: &mut Context) -> Option<Score> {
+    let tablebases = ctx.tablebases.as_ref()?;
+
+    if pos.half_move_clock() != 0 || !tablebases.can_probe(pos) {
+        return None;
+    }
+
+    let wdl = tablebases.probe_wdl(pos)?;
+    ctx.count_tb_hit();
+
+    Some(match wdl {
+        wdl::WIN => TB_WIN_SCORE,
+        wdl::LOSS => -TB_WIN_SCORE,
+        _ => DRAW_SCORE,
+    })
+}
@@ -0,0 +1,267 @@
+use std::time::{Duration, Instant};
+
+use crate::macros::ternary;
+
+/// Time kept in reserve for communication with the GUI.
+const MOVE_OVERHEAD: u64 = 30;
+
+/// Assumed number of moves left in the game when the time control doesn't say.
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+/// How many times the soft limit the hard limit can be.
+const HARD_LIMIT_FACTOR: u64 = 4;
+
+/// The share of the clock the hard limit never exceeds, in percent. The rest absorbs the delay
+/// between two checks of the clock and a slow GUI on the last move before a time control.
+const MAX_CLOCK_USAGE: u64 = 80;
+
+/// Bounds of the factor applied to the soft limit as the search proceeds.
+const MIN_TIME_SCALE: f64 = 0.5;
+const MAX_TIME_SCALE: f64 = 3.0;
+
+/// Stopping conditions of a search. Times are in milliseconds.
+#[derive(Clone, Copy)]
+pub(crate) struct Limits {
+    pub(crate) depth: usize,
+    /// Remaining time on the clock of the side to move.
+    pub(crate) time: Option<u64>,
+    pub(crate) increment: u64,
+    /// Moves left until the next time control, if any.
+    pub(crate) moves_to_go: Option<u64>,
+    /// Fixed time to spend on the move.
+    pub(crate) move_time: Option<u64>,
+    pub(crate) nodes: Option<u64>,
+    /// Searches only for a forced mate within that many moves.
+    pub(crate) mate: Option<usize>,
+    /// The clock only starts on `ponderhit`, the search runs until then.
+    pub(crate) ponder: bool,
+}
+
+impl Limits {
+    pub(crate) const fn depth(depth: usize) -> Self {
+        Self {
+            depth,
+            time: None,
+            increment: 0,
+            moves_to_go: None,
+            move_time: None,
+            nodes: None,
+            mate: None,
+            ponder: false,
+        }
+    }
+}
+
+/// Decides when to stop deepening the search (soft limit) and when to abort it altogether (hard limit).
+pub(crate) struct TimeManager {
+    start: Instant,
+    /// When the clock started, later than `start` when pondering.
+    clock_start: Instant,
+    pondering: bool,
+    soft_limit: Option<Duration>,
+    hard_limit: Option<Duration>,
+    node_limit: Option<u64>,
+    /// Grows when the search is unstable, shrinks when it settles.
+    scale: f64,
+    aspiration_failed: bool,
+}
+
+impl TimeManager {
+    pub(crate) fn new(limits: &Limits) -> Self {
+        let (soft_limit, hard_limit) = match (limits.move_time, limits.time) {
+            (Some(move_time), _) => {
+                let limit = move_time.saturating_sub(MOVE_OVERHEAD).max(1);
+                (Some(limit), Some(limit))
+            }
+            (None, Some(time)) => {
+                let available = time.saturating_sub(MOVE_OVERHEAD).max(1);
+                let moves_to_go = limits.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+                let soft = available / moves_to_go + limits.increment * 3 / 4;
+                let hard = (soft * HARD_LIMIT_FACTOR)
+                    .min(available * MAX_CLOCK_USAGE / 100)
+                    .max(1);
+                // iterations may run up to the hard limit, so they start well before it
+                let soft = soft.min(hard / 2).max(1);
+                (Some(soft), Some(hard))
+            }
+            (None, None) => (None, None),
+        };
+
+        Self {
+            start: Instant::now(),
+            clock_start: Instant::now(),
+            pondering: limits.ponder,
+            soft_limit: soft_limit.map(Duration::from_millis),
+            hard_limit: hard_limit.map(Duration::from_millis),
+            node_limit: limits.nodes,
+            scale: 1.0,
+            aspiration_failed: false,
+        }
+    }
+
+    pub(crate) fn unlimited() -> Self {
+        Self::new(&Limits::depth(super::MAX_DEPTH))
+    }
+
+    /// Only keeps the node limit, the main thread watches the clock for the helpers.
+    pub(super) fn for_helper(&self) -> Self {
+        Self {
+            node_limit: self.node_limit,
+            ..Self::unlimited()
+        }
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub(super) fn is_node_limit_reached(&self, nodes: u64) -> bool {
+        self.node_limit
+            .is_some_and(|node_limit| nodes >= node_limit)
+    }
+
+    pub(super) fn is_hard_limit_reached(&self) -> bool {
+        !self.pondering
+            && self
+                .hard_limit
+                .is_some_and(|hard_limit| self.clock_start.elapsed() >= hard_limit)
+    }
+
+    /// Whether a new iteration shouldn't be started.
+    pub(super) fn is_soft_limit_reached(&self) -> bool {
+        match (self.soft_limit, self.hard_limit) {
+            (Some(soft_limit), Some(hard_limit)) if !self.pondering => {
+                self.clock_start.elapsed() >= soft_limit.mul_f64(self.scale).min(hard_limit)
+            }
+            _ => false,
+        }
+    }
+
+    pub(super) const fn is_pondering(&self) -> bool {
+        self.pondering
+    }
+
+    /// The opponent played the expected move: the clock starts now.
+    pub(super) fn on_ponderhit(&mut self) {
+        self.pondering = false;
+        self.clock_start = Instant::now();
+    }
+
+    pub(super) const fn on_aspiration_fail(&mut self) {
+        self.aspiration_failed = true;
+    }
+
+    /// Gives the search more time when the best move changed or the aspiration window failed
+    /// during the last iteration, and less when it didn't.
+    pub(super) fn on_iteration(&mut self, best_mv_changed: bool) {
+        self.scale = ternary!(
+            best_mv_changed || self.aspiration_failed,
+            (self.scale * 1.5).min(MAX_TIME_SCALE),
+            (self.scale * 0.9).max(MIN_TIME_SCALE)
+        );
+        self.aspiration_failed = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(time: u64, increment: u64, moves_to_go: Option<u64>) -> Limits {
+        Limits {
+            time: Some(time),
+            increment,
+            moves_to_go,
+            ..Limits::depth(super::super::MAX_DEPTH)
+        }
+    }
+
+    #[test]
+    fn move_time() {
+        let tm = TimeManager::new(&Limits {
+            move_time: Some(1000),
+            ..Limits::depth(10)
+        });
+
+        assert_eq!(tm.soft_limit, Some(Duration::from_millis(970)));
+        assert_eq!(tm.hard_limit, tm.soft_limit);
+    }
+
+    #[test]
+    fn sudden_death() {
+        let tm = TimeManager::new(&limits(60_030, 0, None));
+
+        assert_eq!(tm.soft_limit, Some(Duration::from_millis(2000)));
+        assert_eq!(tm.hard_limit, Some(Duration::from_millis(8000)));
+    }
+
+    #[test]
+    fn increment() {
+        let tm = TimeManager::new(&limits(30_030, 2000, None));
+
+        assert_eq!(tm.soft_limit, Some(Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn last_move_before_time_control() {
+        let tm = TimeManager::new(&limits(5030, 0, Some(1)));
+
+        assert_eq!(tm.soft_limit, Some(Duration::from_millis(2000)));
+        assert_eq!(tm.hard_limit, Some(Duration::from_millis(4000)));
+    }
+
+    #[test]
+    fn never_exceeds_clock() {
+        let tm = TimeManager::new(&limits(100, 5000, None));
+
+        assert_eq!(tm.soft_limit, Some(Duration::from_millis(28)));
+        assert_eq!(tm.hard_limit, Some(Duration::from_millis(56)));
+    }
+
+    #[test]
+    fn scale_with_instability() {
+        let mut tm = TimeManager::new(&limits(60_000, 0, None));
+
+        tm.on_iteration(true);
+        assert!(tm.scale > 1.0);
+
+        for _ in 0..20 {
+            tm.on_iteration(false);
+        }
+        assert_eq!(tm.scale, MIN_TIME_SCALE);
+
+        tm.on_aspiration_fail();
+        tm.on_iteration(false);
+        assert!(tm.scale > MIN_TIME_SCALE);
+    }
+
+    #[test]
+    fn node_limit() {
+        let tm = TimeManager::new(&Limits {
+            nodes: Some(1000),
+            ..Limits::depth(10)
+        });
+
+        assert!(!tm.is_node_limit_reached(999));
+        assert!(tm.is_node_limit_reached(1000));
+        assert!(!tm.is_hard_limit_reached());
+    }
+
+    #[test]
+    fn ponder() {
+        let mut tm = TimeManager::new(&Limits {
+            move_time: Some(31),
+            ponder: true,
+            ..Limits::depth(10)
+        });
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert!(!tm.is_soft_limit_reached());
+        assert!(!tm.is_hard_limit_reached());
+
+        tm.on_ponderhit();
+        assert!(!tm.is_hard_limit_reached());
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(tm.is_hard_limit_reached());
+    }
+}
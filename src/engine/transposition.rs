@@ -1,10 +1,12 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use crate::{
     engine::score::{Score, score_from_tt},
-    game::moves::{Move, NULL_MOVE},
+    game::moves::Move,
     macros::ternary,
 };
 
-const TABLE_SIZE: usize = 1 << 23;
+pub(crate) const DEFAULT_SIZE_MB: usize = 256;
 
 pub(crate) mod flags {
     use super::Score;
@@ -60,33 +62,92 @@ impl Entry {
     }
 }
 
-pub(crate) fn create_table() -> Table {
-    let null_entry = Entry::new(flags::NONE, 0, 0, 0, NULL_MOVE);
-    let table: Vec<Entry> = vec![null_entry; TABLE_SIZE];
-    table.into_boxed_slice()
+/// Entries are packed into two words, the key being the hash XORed with the data, so that an
+/// entry torn by two threads writing at the same time matches no position.
+/// From the lowest bits: the move, the flag, the depth and the signed score.
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+const MOVE_BITS: u32 = 27;
+const FLAG_BITS: u32 = 2;
+const DEPTH_BITS: u32 = 8;
+const OFFSET_FLAG: u32 = MOVE_BITS;
+const OFFSET_DEPTH: u32 = OFFSET_FLAG + FLAG_BITS;
+const OFFSET_SCORE: u32 = OFFSET_DEPTH + DEPTH_BITS;
+
+/// Shared by the threads of a search without locking.
+pub(crate) struct Table {
+    slots: Box<[Slot]>,
+}
+
+impl Entry {
+    const fn pack(&self) -> u64 {
+        self.mv as u64
+            | (self.flag as u64) << OFFSET_FLAG
+            | (self.depth as u64) << OFFSET_DEPTH
+            | (self.score as u64) << OFFSET_SCORE
+    }
+
+    const fn unpack(hash: u64, data: u64) -> Self {
+        Self {
+            flag: (data >> OFFSET_FLAG & ((1 << FLAG_BITS) - 1)) as flags::Flag,
+            hash,
+            score: (data as i64 >> OFFSET_SCORE) as Score,
+            depth: (data >> OFFSET_DEPTH & ((1 << DEPTH_BITS) - 1)) as usize,
+            mv: (data & ((1 << MOVE_BITS) - 1)) as Move,
+        }
+    }
+}
+
+/// Creates a table of at most `size_mb` megabytes.
+/// The number of entries is rounded down to a power of 2.
+pub(crate) fn create_table(size_mb: usize) -> Table {
+    let nb_entries = (size_mb << 20) / size_of::<Slot>();
+    let nb_entries = 1 << (usize::BITS - 1 - nb_entries.max(1).leading_zeros());
+    let slots = (0..nb_entries)
+        .map(|_| Slot {
+            key: AtomicU64::new(0),
+            data: AtomicU64::new(0),
+        })
+        .collect();
+
+    Table { slots }
 }
 
-const fn get_index(hash: u64) -> usize {
-    const INDEX_MASK: usize = TABLE_SIZE - 1;
+/// Empties the table without reallocating it.
+pub(crate) fn clear_table(tt: &Table) {
+    for slot in &tt.slots {
+        slot.key.store(0, Ordering::Relaxed);
+        slot.data.store(0, Ordering::Relaxed);
+    }
+}
 
-    hash as usize & INDEX_MASK
+const fn get_slot(tt: &Table, hash: u64) -> &Slot {
+    &tt.slots[hash as usize & (tt.slots.len() - 1)]
 }
 
-pub(crate) const fn get_entry(tt: &Table, hash: u64) -> Option<Entry> {
-    let entry = tt[get_index(hash)];
+pub(crate) fn get_entry(tt: &Table, hash: u64) -> Option<Entry> {
+    let slot = get_slot(tt, hash);
+    let data = slot.data.load(Ordering::Relaxed);
+    let is_match = slot.key.load(Ordering::Relaxed) ^ data == hash && data != 0;
 
-    ternary!(entry.hash == hash, Some(entry), None)
+    ternary!(is_match, Some(Entry::unpack(hash, data)), None)
 }
 
-pub(crate) const fn set_entry(tt: &mut Table, entry: Entry) {
-    let index = get_index(entry.hash);
-    let prev_entry = tt[index];
+pub(crate) fn set_entry(tt: &Table, entry: Entry) {
+    let slot = get_slot(tt, entry.hash);
+    let prev_data = slot.data.load(Ordering::Relaxed);
+    let prev_entry = Entry::unpack(0, prev_data);
 
-    if prev_entry.flag == flags::NONE
+    if prev_data == 0
         || entry.depth > prev_entry.depth
         || entry.flag == flags::EXACT && prev_entry.flag != flags::EXACT
     {
-        tt[index] = entry;
+        let data = entry.pack();
+        slot.key.store(entry.hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
     }
 }
 
@@ -127,5 +188,3 @@ pub(crate) fn cached_score(
 
     None
 }
-
-pub(crate) type Table = Box<[Entry]>;
@@ -0,0 +1,211 @@
+use crate::game::{
+    moves::{
+        Move,
+        san::{self, SANError},
+    },
+    position::{FENError, Position},
+};
+
+/// A position of a test suite with the operations the engine is judged by.
+pub(crate) struct EPDRecord {
+    /// Completed with the `hmvc` and `fmvn` operations, or `0 1`.
+    pub(crate) fen: String,
+    /// `bm`: the engine should play one of these moves.
+    pub(crate) best_moves: Vec<Move>,
+    /// `am`: the engine should play none of these moves.
+    pub(crate) avoid_moves: Vec<Move>,
+    /// `dm`: the side to move mates in this number of moves.
+    pub(crate) direct_mate: Option<u32>,
+    pub(crate) id: Option<String>,
+    pub(crate) comment: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum EPDError {
+    InvalidFormat(String),
+    InvalidFEN(FENError),
+    /// The opcode and the move.
+    InvalidMove(String, SANError),
+    /// The opcode and its operands.
+    InvalidOperand(String, String),
+}
+
+/// Parses a line of the form `<board> <color> <castling> <en passant> <opcode> <operands>; ...`.
+/// Operations other than `bm`, `am`, `dm`, `id`, `c0`, `hmvc` and `fmvn` are ignored.
+pub(crate) fn parse_epd(line: &str) -> Result<EPDRecord, EPDError> {
+    let fields = line.trim().splitn(5, ' ').collect::<Vec<&str>>();
+
+    if fields.len() < 4 {
+        return Err(EPDError::InvalidFormat(line.to_owned()));
+    }
+
+    let operations = parse_operations(fields.get(4).copied().unwrap_or_default());
+    let operand = |opcode: &str| {
+        operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.join(" "))
+    };
+    let counter = |opcode: &str, default: &str| {
+        let operand = operand(opcode).unwrap_or(default.to_string());
+
+        match operand.parse::<u16>() {
+            Ok(_) => Ok(operand),
+            Err(_) => Err(EPDError::InvalidOperand(opcode.to_string(), operand)),
+        }
+    };
+
+    let fen = format!(
+        "{} {} {}",
+        fields[..4].join(" "),
+        counter("hmvc", "0")?,
+        counter("fmvn", "1")?
+    );
+    let pos = Position::from_fen(&fen).map_err(EPDError::InvalidFEN)?;
+
+    let direct_mate = match operand("dm") {
+        Some(operand) => match operand.parse::<u32>() {
+            Ok(moves) => Some(moves),
+            Err(_) => return Err(EPDError::InvalidOperand("dm".to_string(), operand)),
+        },
+        None => None,
+    };
+
+    Ok(EPDRecord {
+        best_moves: parse_moves(&pos, &operations, "bm")?,
+        avoid_moves: parse_moves(&pos, &operations, "am")?,
+        direct_mate,
+        id: operand("id"),
+        comment: operand("c0"),
+        fen,
+    })
+}
+
+/// Splits the operations into their opcode and operands, keeping quoted strings whole.
+fn parse_operations(str: &str) -> Vec<(String, Vec<String>)> {
+    let mut operations = Vec::new();
+    let mut words = Vec::<String>::new();
+    let mut word = String::new();
+    let mut in_string = false;
+    let mut chars = str.chars();
+
+    while let Some(ch) = chars.next() {
+        match (in_string, ch) {
+            (true, '"') => {
+                in_string = false;
+                words.push(std::mem::take(&mut word));
+            }
+            (true, '\\') => word.extend(chars.next()),
+            (true, ch) => word.push(ch),
+            (false, '"') => in_string = true,
+            (false, ';') | (false, ' ') | (false, '\t') => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+
+                if ch == ';' && !words.is_empty() {
+                    let opcode = words.remove(0);
+                    operations.push((opcode, std::mem::take(&mut words)));
+                }
+            }
+            (false, ch) => word.push(ch),
+        };
+    }
+
+    // the last operation may lack its semicolon
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    if !words.is_empty() {
+        let opcode = words.remove(0);
+        operations.push((opcode, words));
+    }
+
+    operations
+}
+
+fn parse_moves(
+    pos: &Position,
+    operations: &[(String, Vec<String>)],
+    opcode: &str,
+) -> Result<Vec<Move>, EPDError> {
+    operations
+        .iter()
+        .filter(|(name, _)| name == opcode)
+        .flat_map(|(_, operands)| operands)
+        .map(|str| {
+            san::parse_san(pos, str)
+                .map_err(|error| EPDError::InvalidMove(opcode.to_string(), error))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::moves::to_uci;
+
+    use super::*;
+
+    #[test]
+    fn operations() {
+        let epd = parse_epd(
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\"; \
+            c0 \"Qg6=10, Nf7+=3\"",
+        )
+        .unwrap();
+
+        assert_eq!(
+            epd.fen,
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1"
+        );
+        assert_eq!(
+            epd.best_moves
+                .iter()
+                .map(|&mv| to_uci(mv))
+                .collect::<Vec<_>>(),
+            ["g3g6"]
+        );
+        assert!(epd.avoid_moves.is_empty());
+        assert_eq!(epd.id.as_deref(), Some("WAC.001"));
+        assert_eq!(epd.comment.as_deref(), Some("Qg6=10, Nf7+=3"));
+        assert_eq!(epd.direct_mate, None);
+    }
+
+    #[test]
+    fn several_moves_and_counters() {
+        let epd = parse_epd(
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - \
+            am Nxe5 Ng5; bm Bc4 d4; dm 7; hmvc 2; fmvn 3;",
+        )
+        .unwrap();
+
+        assert_eq!(
+            epd.fen,
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3"
+        );
+        assert_eq!(epd.best_moves.len(), 2);
+        assert_eq!(epd.avoid_moves.len(), 2);
+        assert_eq!(epd.direct_mate, Some(7));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            parse_epd("8/8/8 w - -"),
+            Err(EPDError::InvalidFEN(_))
+        ));
+        assert!(matches!(
+            parse_epd("k7/8/8/8"),
+            Err(EPDError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            parse_epd("k7/8/8/8/8/8/8/K7 w - - bm Kc3;"),
+            Err(EPDError::InvalidMove(..))
+        ));
+        assert_eq!(
+            parse_epd("k7/8/8/8/8/8/8/K7 w - - dm x;").err(),
+            Some(EPDError::InvalidOperand("dm".to_string(), "x".to_string()))
+        );
+    }
+}
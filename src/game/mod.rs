@@ -1,3 +1,5 @@
 pub(crate) mod board;
+pub(crate) mod epd;
 pub(crate) mod moves;
+pub(crate) mod pgn;
 pub(crate) mod position;
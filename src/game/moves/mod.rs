@@ -2,6 +2,8 @@ mod attacks;
 pub(crate) mod castling;
 pub(crate) mod encoding;
 mod move_list;
+pub(crate) mod san;
+pub(crate) mod uci;
 
 pub(crate) type Move = u32;
 
@@ -9,3 +11,4 @@ pub(crate) const NULL_MOVE: Move = 0;
 
 pub(crate) use attacks::piece_attacks;
 pub(crate) use move_list::MoveList;
+pub(crate) use uci::to_uci;
@@ -0,0 +1,295 @@
+use crate::{
+    game::{
+        board::{lines, pieces, squares, wings},
+        moves::{Move, castling, encoding},
+        position::Position,
+    },
+    macros::ternary,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SANError {
+    InvalidFormat(String),
+    IllegalMove(String),
+    AmbiguousMove(String),
+}
+
+/// Formats a legal move in standard algebraic notation, e.g. `Nbd2`, `exd6`, `e8=Q+` or `O-O#`.
+/// The move is played and undone to find out whether it gives check or mate.
+pub(crate) fn to_san(pos: &mut Position, mv: Move) -> String {
+    let mut result = stringify_move(pos, mv);
+
+    let undo_info = pos.undo_info();
+    pos.play_move(mv);
+
+    if pos.is_check() {
+        result.push(ternary!(pos.legal_moves().is_empty(), '#', '+'));
+    }
+
+    pos.undo_move(mv, undo_info);
+    result
+}
+
+fn stringify_move(pos: &Position, mv: Move) -> String {
+    let src_sq = encoding::src_square(mv);
+    let dest_sq = encoding::dest_square(mv);
+
+    if encoding::is_castling(mv) {
+        let result = ternary!(
+            castling::get_wing(src_sq, dest_sq) == wings::QUEEN_SIDE,
+            "O-O-O",
+            "O-O"
+        );
+        return result.to_string();
+    }
+
+    let src_piece = encoding::src_piece(mv);
+    let is_capture = encoding::is_capture(mv);
+    let mut result = String::new();
+
+    if pieces::is_pawn(src_piece) {
+        if is_capture {
+            result.push(lines::file_name(squares::file_of(src_sq)));
+            result.push('x');
+        }
+
+        result.push_str(&squares::name_of(dest_sq));
+
+        if encoding::is_promotion(mv) {
+            result.push('=');
+            result.push(pieces::initial_of(encoding::promoted(mv)).to_ascii_uppercase());
+        }
+
+        return result;
+    }
+
+    result.push(pieces::initial_of(src_piece).to_ascii_uppercase());
+    result.push_str(&disambiguation(pos, mv));
+
+    if is_capture {
+        result.push('x');
+    }
+
+    result.push_str(&squares::name_of(dest_sq));
+    result
+}
+
+/// The file, rank or square of departure needed when several pieces of the same kind
+/// can reach the destination square.
+fn disambiguation(pos: &Position, mv: Move) -> String {
+    let src_sq = encoding::src_square(mv);
+    let dest_sq = encoding::dest_square(mv);
+    let src_piece = encoding::src_piece(mv);
+    let mut is_ambiguous = false;
+    let mut same_file = false;
+    let mut same_rank = false;
+
+    for &other in &pos.legal_moves() {
+        let other_src_sq = encoding::src_square(other);
+
+        if other_src_sq == src_sq
+            || encoding::src_piece(other) != src_piece
+            || encoding::dest_square(other) != dest_sq
+        {
+            continue;
+        }
+
+        is_ambiguous = true;
+        same_file |= squares::file_of(other_src_sq) == squares::file_of(src_sq);
+        same_rank |= squares::rank_of(other_src_sq) == squares::rank_of(src_sq);
+    }
+
+    match (is_ambiguous, same_file, same_rank) {
+        (false, _, _) => String::new(),
+        (true, false, _) => lines::file_name(squares::file_of(src_sq)).to_string(),
+        (true, true, false) => lines::rank_name(squares::rank_of(src_sq)).to_string(),
+        (true, true, true) => squares::name_of(src_sq),
+    }
+}
+
+/// Finds the legal move matching a string in standard algebraic notation.
+/// Check marks, annotations and an `e.p.` suffix are ignored, castling may be written
+/// with zeros and the `=` of promotions may be omitted.
+pub(crate) fn parse_san(pos: &Position, san: &str) -> Result<Move, SANError> {
+    let str = san
+        .trim()
+        .trim_end_matches("e.p.")
+        .trim_end()
+        .trim_end_matches(['+', '#', '!', '?']);
+
+    if let Some(wing) = parse_castling(str) {
+        let color = pos.get_active_color();
+        let src_sq = castling::king_src_square(color);
+        let dest_sq = castling::king_dest_square(color, wing);
+
+        return find_move(pos, san, |mv| {
+            encoding::is_castling(mv)
+                && encoding::src_square(mv) == src_sq
+                && encoding::dest_square(mv) == dest_sq
+        });
+    }
+
+    let invalid = || SANError::InvalidFormat(san.to_owned());
+    let mut chars = str.chars().collect::<Vec<char>>();
+
+    let piece_type = match chars.first() {
+        Some(&initial) if "NBRQK".contains(initial) => {
+            chars.remove(0);
+            pieces::type_of(pieces::from_initial(initial))
+        }
+        Some(_) => pieces::piece_types::PAWN,
+        None => return Err(invalid()),
+    };
+
+    let promoted_type = match chars.last() {
+        Some(&initial) if "NBRQ".contains(initial) => {
+            chars.pop();
+            chars.pop_if(|&mut ch| ch == '=');
+            Some(pieces::type_of(pieces::from_initial(initial)))
+        }
+        _ => None,
+    };
+
+    let dest_sq = match chars.split_off(chars.len().saturating_sub(2)).as_slice() {
+        [file @ 'a'..='h', rank @ '1'..='8'] => squares::of(rank_index(*rank), file_index(*file)),
+        _ => return Err(invalid()),
+    };
+    chars.pop_if(|&mut ch| ch == 'x');
+
+    let (src_file, src_rank) = match chars.as_slice() {
+        [] => (None, None),
+        [file @ 'a'..='h'] => (Some(file_index(*file)), None),
+        [rank @ '1'..='8'] => (None, Some(rank_index(*rank))),
+        [file @ 'a'..='h', rank @ '1'..='8'] => (Some(file_index(*file)), Some(rank_index(*rank))),
+        _ => return Err(invalid()),
+    };
+
+    find_move(pos, san, |mv| {
+        let src_sq = encoding::src_square(mv);
+        let promoted = encoding::promoted(mv);
+
+        pieces::type_of(encoding::src_piece(mv)) == piece_type
+            && !encoding::is_castling(mv)
+            && encoding::dest_square(mv) == dest_sq
+            && src_file.is_none_or(|file| squares::file_of(src_sq) == file)
+            && src_rank.is_none_or(|rank| squares::rank_of(src_sq) == rank)
+            && match promoted_type {
+                Some(promoted_type) => {
+                    encoding::is_promotion(mv) && pieces::type_of(promoted) == promoted_type
+                }
+                None => !encoding::is_promotion(mv),
+            }
+    })
+}
+
+fn parse_castling(str: &str) -> Option<usize> {
+    match str {
+        "O-O" | "0-0" => Some(wings::KING_SIDE),
+        "O-O-O" | "0-0-0" => Some(wings::QUEEN_SIDE),
+        _ => None,
+    }
+}
+
+const fn file_index(ch: char) -> usize {
+    (ch as u8 - b'a') as usize
+}
+
+const fn rank_index(ch: char) -> usize {
+    (ch as u8 - b'1') as usize
+}
+
+fn find_move(
+    pos: &Position,
+    san: &str,
+    predicate: impl Fn(Move) -> bool,
+) -> Result<Move, SANError> {
+    let mut matches = pos.legal_moves();
+    matches.retain(predicate);
+
+    match matches.len() {
+        0 => Err(SANError::IllegalMove(san.to_owned())),
+        1 => Ok(matches[0]),
+        _ => Err(SANError::AmbiguousMove(san.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_round_trip(fen: &str, uci: &str, san: &str) {
+        let mut pos = Position::from_fen(fen).unwrap();
+        let mv = pos.parse_uci_move(uci).unwrap();
+
+        assert_eq!(to_san(&mut pos, mv), san);
+        assert_eq!(parse_san(&pos, san), Ok(mv));
+    }
+
+    #[test]
+    fn pawn_moves() {
+        assert_round_trip(Position::START_FEN, "e2e4", "e4");
+        assert_round_trip(
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "e5f6",
+            "exf6",
+        );
+        assert_round_trip("7k/1P6/8/8/8/8/8/K7 w - - 0 1", "b7b8q", "b8=Q+");
+        assert_round_trip("2r4k/1P6/8/8/8/8/8/K7 w - - 0 1", "b7c8n", "bxc8=N");
+    }
+
+    #[test]
+    fn disambiguation() {
+        let fen = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+        assert_round_trip(fen, "b1d2", "Nbd2");
+        assert_round_trip(fen, "f3d2", "Nfd2");
+
+        let fen = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_round_trip(fen, "a1a3", "R1a3");
+        assert_round_trip(fen, "a5a3", "R5a3");
+
+        let fen = "7k/8/8/8/Q1Q5/8/Q7/4K3 w - - 0 1";
+        assert_round_trip(fen, "a4b3", "Qa4b3");
+        assert_round_trip(fen, "c4b3", "Qcb3");
+    }
+
+    #[test]
+    fn castling_and_mate() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_round_trip(fen, "e1g1", "O-O");
+        assert_round_trip(fen, "e1c1", "O-O-O");
+        assert_round_trip("6k1/5ppp/8/8/8/8/8/R3K3 w Q - 0 1", "a1a8", "Ra8#");
+        assert_round_trip("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1", "O-O+");
+    }
+
+    #[test]
+    fn lenient_parsing() {
+        let pos = Position::from_fen("r3k2r/8/8/3pP3/8/8/6P1/R3K2R w KQkq d6 0 1").unwrap();
+
+        assert_eq!(parse_san(&pos, "0-0"), parse_san(&pos, "O-O"));
+        assert_eq!(parse_san(&pos, "0-0-0+"), parse_san(&pos, "O-O-O"));
+        assert_eq!(parse_san(&pos, "exd6 e.p."), parse_san(&pos, "exd6"));
+        assert!(parse_san(&pos, "Ke2!?").is_ok());
+
+        let pos = Position::from_fen("7k/1P6/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(parse_san(&pos, "b8Q"), parse_san(&pos, "b8=Q+"));
+    }
+
+    #[test]
+    fn errors() {
+        let pos = Position::from_fen("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1").unwrap();
+
+        assert_eq!(
+            parse_san(&pos, "Nd2"),
+            Err(SANError::AmbiguousMove("Nd2".to_string()))
+        );
+        assert_eq!(
+            parse_san(&pos, "Nd3"),
+            Err(SANError::IllegalMove("Nd3".to_string()))
+        );
+        assert_eq!(
+            parse_san(&pos, "Nz9"),
+            Err(SANError::InvalidFormat("Nz9".to_string()))
+        );
+        assert!(parse_san(&pos, "").is_err());
+    }
+}
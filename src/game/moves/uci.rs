@@ -0,0 +1,88 @@
+use crate::game::{
+    board::{pieces, squares},
+    moves::{Move, NULL_MOVE, encoding},
+    position::Position,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum UCIMoveError {
+    InvalidFormat(String),
+    IllegalMove(String),
+}
+
+/// Formats a move in long algebraic notation, e.g. `e2e4` or `e7e8q`.
+pub(crate) fn to_uci(mv: Move) -> String {
+    if mv == NULL_MOVE {
+        return "0000".to_string();
+    }
+
+    let mut result = squares::name_of(encoding::src_square(mv));
+    result.push_str(&squares::name_of(encoding::dest_square(mv)));
+
+    if encoding::is_promotion(mv) {
+        let promoted = encoding::promoted(mv);
+        result.push(pieces::initial_of(promoted).to_ascii_lowercase());
+    }
+
+    result
+}
+
+/// Finds the legal move written in long algebraic notation.
+pub(crate) fn parse_uci(pos: &Position, str: &str) -> Result<Move, UCIMoveError> {
+    let chars = str.chars().collect::<Vec<char>>();
+
+    let is_valid = match chars.as_slice() {
+        ['a'..='h', '1'..='8', 'a'..='h', '1'..='8'] => true,
+        ['a'..='h', '1'..='8', 'a'..='h', '1'..='8', promoted] => "nbrq".contains(*promoted),
+        _ => false,
+    };
+
+    if !is_valid {
+        return Err(UCIMoveError::InvalidFormat(str.to_owned()));
+    }
+
+    pos.legal_moves()
+        .as_slice()
+        .iter()
+        .find(|&&mv| to_uci(mv) == str)
+        .copied()
+        .ok_or_else(|| UCIMoveError::IllegalMove(str.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let pos = Position::from_fen("r3k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+
+        for str in ["e1g1", "h1h8", "b7b8q", "b7a8n"] {
+            let mv = pos.parse_uci_move(str).unwrap();
+            assert_eq!(to_uci(mv), str);
+        }
+
+        let mv = pos.parse_uci_move("b7a8r").unwrap();
+        assert!(encoding::is_capture(mv) && encoding::is_promotion(mv));
+        assert!(encoding::is_castling(pos.parse_uci_move("e1g1").unwrap()));
+        assert!(encoding::gives_check(pos.parse_uci_move("h1h8").unwrap()));
+        assert_eq!(to_uci(NULL_MOVE), "0000");
+    }
+
+    #[test]
+    fn errors() {
+        let pos = Position::from_fen(Position::START_FEN).unwrap();
+
+        assert_eq!(
+            pos.parse_uci_move("e2e5"),
+            Err(UCIMoveError::IllegalMove("e2e5".to_string()))
+        );
+
+        for str in ["", "e2", "e2e4x", "e2e9", "i2i4", "e7e8k"] {
+            assert_eq!(
+                pos.parse_uci_move(str),
+                Err(UCIMoveError::InvalidFormat(str.to_string()))
+            );
+        }
+    }
+}
@@ -0,0 +1,4 @@
+mod reader;
+pub(crate) mod writer;
+
+pub(crate) use reader::{Game, read_games};
@@ -0,0 +1,388 @@
+use std::{fmt, iter::Peekable, str::Chars};
+
+use crate::game::{
+    moves::{Move, san},
+    position::Position,
+};
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// A game of a PGN file, replayed from its starting position.
+pub(crate) struct Game {
+    /// Tag pairs in the order they appear.
+    pub(crate) tags: Vec<(String, String)>,
+    /// Taken from the `FEN` tag if present.
+    pub(crate) start_fen: String,
+    /// The moves of the main line.
+    pub(crate) moves: Vec<Move>,
+    /// The game termination marker, `*` if missing.
+    pub(crate) result: String,
+}
+
+impl Game {
+    pub(crate) fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub(crate) fn start_position(&self) -> Position {
+        Position::from_fen(&self.start_fen).unwrap()
+    }
+}
+
+/// Games and plies are numbered from 1.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PGNError {
+    InvalidTag {
+        game: usize,
+        tag: String,
+    },
+    InvalidFEN {
+        game: usize,
+        fen: String,
+    },
+    IllegalMove {
+        game: usize,
+        ply: usize,
+        san: String,
+    },
+    UnbalancedVariation {
+        game: usize,
+    },
+}
+
+impl fmt::Display for PGNError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidTag { game, tag } => write!(f, "invalid tag {} in game {}", tag, game),
+            Self::InvalidFEN { game, fen } => write!(f, "invalid FEN {} in game {}", fen, game),
+            Self::IllegalMove { game, ply, san } => {
+                write!(f, "illegal move {} at ply {} in game {}", san, ply, game)
+            }
+            Self::UnbalancedVariation { game } => {
+                write!(f, "unbalanced variation in game {}", game)
+            }
+        }
+    }
+}
+
+enum Token {
+    Tag(String, String),
+    InvalidTag(String),
+    Move(String),
+    Result(String),
+    VariationStart,
+    VariationEnd,
+}
+
+/// Reads every game of a PGN file. Comments, NAGs and variations are skipped and only the main
+/// line is replayed. A game with an error is reported as such and reading goes on with the next one.
+pub(crate) fn read_games(pgn: &str) -> Vec<Result<Game, PGNError>> {
+    let mut tokens = tokenize(pgn).into_iter().peekable();
+    let mut games = Vec::new();
+
+    while tokens.peek().is_some() {
+        games.push(read_game(&mut tokens, games.len() + 1));
+    }
+
+    games
+}
+
+/// Consumes the tokens of one game, up to its result or the tags of the next one.
+fn read_game(
+    tokens: &mut Peekable<impl Iterator<Item = Token>>,
+    game_number: usize,
+) -> Result<Game, PGNError> {
+    let mut tags = Vec::new();
+    let mut error = None;
+
+    while let Some(token) =
+        tokens.next_if(|token| matches!(token, Token::Tag(..) | Token::InvalidTag(_)))
+    {
+        match token {
+            Token::Tag(name, value) => tags.push((name, value)),
+            Token::InvalidTag(tag) => {
+                error.get_or_insert(PGNError::InvalidTag {
+                    game: game_number,
+                    tag,
+                });
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    let start_fen = tags
+        .iter()
+        .find(|(name, _)| name == "FEN")
+        .map_or(Position::START_FEN.to_string(), |(_, fen)| fen.clone());
+    let mut pos = Position::from_fen(&start_fen).unwrap_or_else(|_| {
+        error.get_or_insert(PGNError::InvalidFEN {
+            game: game_number,
+            fen: start_fen.clone(),
+        });
+        Position::from_fen(Position::START_FEN).unwrap()
+    });
+    let mut moves = Vec::new();
+    let mut depth = 0usize;
+    let mut result = None;
+
+    while let Some(token) =
+        tokens.next_if(|token| !matches!(token, Token::Tag(..) | Token::InvalidTag(_)))
+    {
+        match token {
+            Token::VariationStart => depth += 1,
+            Token::VariationEnd => match depth.checked_sub(1) {
+                Some(d) => depth = d,
+                None => {
+                    error.get_or_insert(PGNError::UnbalancedVariation { game: game_number });
+                }
+            },
+            Token::Move(str) if depth == 0 && error.is_none() => match san::parse_san(&pos, &str) {
+                Ok(mv) => {
+                    pos.play_move(mv);
+                    moves.push(mv);
+                }
+                Err(_) => {
+                    error = Some(PGNError::IllegalMove {
+                        game: game_number,
+                        ply: moves.len() + 1,
+                        san: str,
+                    });
+                }
+            },
+            Token::Result(str) if depth == 0 => {
+                result = Some(str);
+                break;
+            }
+            _ => {}
+        };
+    }
+
+    if depth > 0 {
+        error.get_or_insert(PGNError::UnbalancedVariation { game: game_number });
+    }
+
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    let result = result
+        .or_else(|| {
+            tags.iter()
+                .find(|(name, _)| name == "Result")
+                .map(|(_, value)| value.clone())
+        })
+        .unwrap_or("*".to_string());
+
+    Ok(Game {
+        tags,
+        start_fen,
+        moves,
+        result,
+    })
+}
+
+fn tokenize(pgn: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = pgn.chars().peekable();
+    let mut is_line_start = true;
+
+    while let Some(ch) = chars.next() {
+        let was_line_start = is_line_start;
+        is_line_start = ch == '\n';
+
+        match ch {
+            // escape mechanism: the rest of the line is ignored
+            '%' if was_line_start => skip_line(&mut chars),
+            ';' => skip_line(&mut chars),
+            '{' => {
+                for ch in chars.by_ref() {
+                    if ch == '}' {
+                        break;
+                    }
+                }
+            }
+            '[' => tokens.push(read_tag(&mut chars)),
+            '(' => tokens.push(Token::VariationStart),
+            ')' => tokens.push(Token::VariationEnd),
+            '$' => while chars.next_if(char::is_ascii_digit).is_some() {},
+            ch if ch.is_whitespace() => {}
+            _ => {
+                let mut word = ch.to_string();
+
+                while let Some(ch) =
+                    chars.next_if(|&ch| !ch.is_whitespace() && !"{}[]();$".contains(ch))
+                {
+                    word.push(ch);
+                }
+
+                if let Some(token) = read_word(&word) {
+                    tokens.push(token);
+                }
+            }
+        };
+    }
+
+    tokens
+}
+
+fn skip_line(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|&ch| ch != '\n').is_some() {}
+}
+
+/// `[Name "value"]`, the opening bracket being already consumed.
+fn read_tag(chars: &mut Peekable<Chars>) -> Token {
+    let mut raw = String::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut value = String::new();
+
+    for ch in chars.by_ref() {
+        match (in_string, escaped, ch) {
+            (false, _, ']') => break,
+            (false, _, '"') => in_string = true,
+            (true, false, '\\') => escaped = true,
+            (true, false, '"') => in_string = false,
+            (true, _, ch) => {
+                value.push(ch);
+                escaped = false;
+            }
+            (false, _, _) => {}
+        };
+
+        if ch != ']' {
+            raw.push(ch);
+        }
+    }
+
+    let name = raw.split_whitespace().next().unwrap_or_default();
+
+    if name.is_empty() || !name.chars().all(|ch| ch.is_alphanumeric() || ch == '_') || in_string {
+        return Token::InvalidTag(format!("[{}]", raw));
+    }
+
+    Token::Tag(name.to_string(), value)
+}
+
+/// Move numbers are dropped, possibly leaving a move glued to them as in `1.e4`.
+/// Digits not followed by a dot are kept so that `0-0` still reads as castling.
+fn read_word(word: &str) -> Option<Token> {
+    if RESULTS.contains(&word) {
+        return Some(Token::Result(word.to_string()));
+    }
+
+    let after_number = word.trim_start_matches(|ch: char| ch.is_ascii_digit());
+    let str = match after_number.strip_prefix('.') {
+        Some(rest) => rest.trim_start_matches('.'),
+        None if after_number.is_empty() => after_number,
+        None => word,
+    };
+
+    // a suffix some files add to en passant captures
+    if str.is_empty() || str == "e.p." {
+        return None;
+    }
+
+    Some(Token::Move(str.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::moves::to_uci;
+
+    use super::*;
+
+    const PGN: &str = r#"[Event "Casual game"]
+[Site "?"]
+[White "Anderssen, Adolf"]
+[Black "Kieseritzky, Lionel"]
+[Result "1-0"]
+
+1.e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 b5!? {The Bryan Counter Gambit.} 5. Bxb5 Nf6
+6. Nf3 Qh6 ( 6... Qh5 7. d3 (7. Nc3) ) 7. d3 $1 Nh5 ; Threatening Ng3+
+8. Nh4 1-0
+
+[Event "Broken"]
+[Result "*"]
+
+1. e4 e5 2. Ke3 Nc6 *
+
+% a line ignored by the escape mechanism
+[Event "From a position"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"]
+
+1. e4 Kd7 2. e5 1/2-1/2
+"#;
+
+    #[test]
+    fn read_collection() {
+        let games = read_games(PGN);
+        assert_eq!(games.len(), 3);
+
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(game.tag("White"), Some("Anderssen, Adolf"));
+        assert_eq!(game.result, "1-0");
+        assert_eq!(game.moves.len(), 15);
+        assert_eq!(to_uci(game.moves[14]), "f3h4");
+
+        assert_eq!(
+            games[1].as_ref().err(),
+            Some(&PGNError::IllegalMove {
+                game: 2,
+                ply: 3,
+                san: "Ke3".to_string()
+            })
+        );
+        assert_eq!(
+            games[1].as_ref().err().unwrap().to_string(),
+            "illegal move Ke3 at ply 3 in game 2"
+        );
+
+        let game = games[2].as_ref().unwrap();
+        assert_eq!(game.start_fen, "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        assert_eq!(game.moves.len(), 3);
+        assert_eq!(game.result, "1/2-1/2");
+    }
+
+    #[test]
+    fn tags_with_escapes() {
+        let games = read_games("[Event \"The \\\"Immortal\\\" game\"]\n[Round \"1\"]\n\n1. d4 *");
+        let game = games[0].as_ref().unwrap();
+
+        assert_eq!(game.tag("Event"), Some("The \"Immortal\" game"));
+        assert_eq!(game.tag("Round"), Some("1"));
+        assert_eq!(game.moves.len(), 1);
+    }
+
+    #[test]
+    fn game_errors() {
+        let games = read_games(
+            "[FEN \"not a fen\"]\n\n1. e4 *\n\n1. e4 (1. d4 *\n\n[Event \"Ok\"]\n\n1. Nf3 Nf6 0-1",
+        );
+
+        assert!(matches!(
+            games[0],
+            Err(PGNError::InvalidFEN { game: 1, .. })
+        ));
+        assert!(matches!(
+            games[1],
+            Err(PGNError::UnbalancedVariation { game: 2 })
+        ));
+        assert_eq!(games[2].as_ref().unwrap().moves.len(), 2);
+    }
+
+    #[test]
+    fn zero_castling() {
+        let games = read_games(
+            "1. d4 d5 2. Nc3 Nc6 3. Bf4 Bf5 4. Qd2 Qd7 5. 0-0-0 e6 6. e3 Nf6 7. f3 Be7 8. g4 0-0 *",
+        );
+        let game = games[0].as_ref().unwrap();
+
+        assert_eq!(game.moves.len(), 16);
+        assert_eq!(to_uci(game.moves[8]), "e1c1");
+        assert_eq!(to_uci(game.moves[15]), "e8g8");
+    }
+}
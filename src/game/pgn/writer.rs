@@ -0,0 +1,260 @@
+use crate::{
+    engine::{Score, is_mate_score, moves_to_mate},
+    game::{
+        board::colors,
+        moves::{Move, san},
+        position::Position,
+    },
+    macros::ternary,
+};
+
+/// Export format lines must stay under 80 columns.
+const MAX_LINE_LENGTH: usize = 79;
+
+/// The tags every exported game starts with, in this order, and their values when unknown.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// The search result behind a move, written after it as `{[%eval +0.35/12]}`.
+#[derive(Clone, Copy)]
+pub(crate) struct Eval {
+    /// From the point of view of the side that played the move.
+    pub(crate) score: Score,
+    pub(crate) depth: usize,
+}
+
+/// Writes a game in PGN export format. Missing Seven Tag Roster tags are filled in with unknown
+/// values, `SetUp` and `FEN` tags are added when the game doesn't start from the initial position
+/// and the `Result` tag doubles as the game termination marker.
+/// `evals` may be empty or hold one optional evaluation per move.
+pub(crate) fn write_game(
+    pos: &mut Position,
+    moves: &[Move],
+    tags: &[(&str, &str)],
+    evals: &[Option<Eval>],
+) -> String {
+    let mut output = String::new();
+    let find_tag = |name: &str| tags.iter().find(|(tag_name, _)| *tag_name == name);
+    let result = find_tag("Result").map_or("*", |&(_, value)| value);
+
+    for (name, default) in SEVEN_TAG_ROSTER {
+        let value = find_tag(name).map_or(default, |&(_, value)| value);
+        output.push_str(&write_tag(name, value));
+    }
+
+    let fen = pos.to_fen();
+
+    if fen != Position::START_FEN {
+        output.push_str(&write_tag("SetUp", "1"));
+        output.push_str(&write_tag("FEN", &fen));
+    }
+
+    for &(name, value) in tags {
+        let is_roster_tag = SEVEN_TAG_ROSTER
+            .iter()
+            .any(|&(roster_name, _)| roster_name == name);
+
+        if !is_roster_tag && name != "SetUp" && name != "FEN" {
+            output.push_str(&write_tag(name, value));
+        }
+    }
+
+    output.push('\n');
+    output.push_str(&wrap(&movetext(pos, moves, evals, result)));
+    output.push('\n');
+    output
+}
+
+fn write_tag(name: &str, value: &str) -> String {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("[{} \"{}\"]\n", name, value)
+}
+
+/// The elements of the movetext, to be separated by spaces.
+fn movetext(
+    pos: &mut Position,
+    moves: &[Move],
+    evals: &[Option<Eval>],
+    result: &str,
+) -> Vec<String> {
+    let mut elements = Vec::new();
+    let mut undo_infos = Vec::new();
+    let mut mv_number = pos.full_move_number();
+    // a move of Black needs its number when it doesn't follow one of White
+    let mut needs_number = true;
+
+    for (i, &mv) in moves.iter().enumerate() {
+        let color = pos.get_active_color();
+
+        if color == colors::WHITE {
+            elements.push(format!("{}.", mv_number));
+        } else if needs_number {
+            elements.push(format!("{}...", mv_number));
+        }
+
+        elements.push(san::to_san(pos, mv));
+        needs_number = false;
+
+        if let Some(eval) = evals.get(i).copied().flatten() {
+            let score = ternary!(color == colors::WHITE, eval.score, -eval.score);
+            elements.push(format!(
+                "{{[%eval {}/{}]}}",
+                stringify_eval(score),
+                eval.depth
+            ));
+            needs_number = true;
+        }
+
+        undo_infos.push(pos.undo_info());
+        pos.play_move(mv);
+
+        if color == colors::BLACK {
+            mv_number += 1;
+        }
+    }
+
+    for i in (0..moves.len()).rev() {
+        pos.undo_move(moves[i], undo_infos[i]);
+    }
+
+    elements.push(result.to_string());
+    elements
+}
+
+/// In pawns from White's point of view, or as a number of moves to mate, e.g. `#-3`.
+fn stringify_eval(score: Score) -> String {
+    if is_mate_score(score) {
+        return format!("#{}", moves_to_mate(score));
+    }
+
+    format!("{:+.2}", score as f64 / 1000.0)
+}
+
+fn wrap(elements: &[String]) -> String {
+    let mut output = String::new();
+    let mut line_length = 0;
+
+    for element in elements {
+        if line_length > 0 && line_length + 1 + element.len() > MAX_LINE_LENGTH {
+            output.push('\n');
+            line_length = 0;
+        }
+
+        if line_length > 0 {
+            output.push(' ');
+            line_length += 1;
+        }
+
+        output.push_str(element);
+        line_length += element.len();
+    }
+
+    output.push('\n');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::pgn::read_games;
+
+    use super::*;
+
+    fn play(pos: &Position, moves: &str) -> Vec<Move> {
+        let mut pos = pos.clone();
+
+        moves
+            .split(' ')
+            .map(|str| {
+                let mv = pos.parse_uci_move(str).unwrap();
+                pos.play_move(mv);
+                mv
+            })
+            .collect()
+    }
+
+    #[test]
+    fn seven_tag_roster() {
+        let mut pos = Position::from_fen(Position::START_FEN).unwrap();
+        let moves = play(&pos, "e2e4 e7e5 g1f3");
+        let pgn = write_game(
+            &mut pos,
+            &moves,
+            &[
+                ("White", "Engine"),
+                ("Result", "1-0"),
+                ("Opening", "\"King's\" pawn"),
+            ],
+            &[],
+        );
+
+        assert_eq!(
+            pgn,
+            "[Event \"?\"]\n\
+            [Site \"?\"]\n\
+            [Date \"????.??.??\"]\n\
+            [Round \"?\"]\n\
+            [White \"Engine\"]\n\
+            [Black \"?\"]\n\
+            [Result \"1-0\"]\n\
+            [Opening \"\\\"King's\\\" pawn\"]\n\
+            \n\
+            1. e4 e5 2. Nf3 1-0\n\
+            \n"
+        );
+        assert_eq!(pos.to_fen(), Position::START_FEN);
+    }
+
+    #[test]
+    fn evals_and_setup() {
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 12";
+        let mut pos = Position::from_fen(fen).unwrap();
+        let moves = play(&pos, "e8d7 e2e4 d7e6");
+        let evals = [
+            None,
+            Some(Eval {
+                score: 350,
+                depth: 12,
+            }),
+            Some(Eval {
+                score: -1200,
+                depth: 11,
+            }),
+        ];
+        let pgn = write_game(&mut pos, &moves, &[], &evals);
+
+        assert!(pgn.contains(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen)));
+        assert!(
+            pgn.ends_with(
+                "\n12... Kd7 13. e4 {[%eval +0.35/12]} 13... Ke6 {[%eval +1.20/11]} *\n\n"
+            )
+        );
+    }
+
+    #[test]
+    fn wrap_and_read_back() {
+        let mut pos = Position::from_fen(Position::START_FEN).unwrap();
+        let moves = play(
+            &pos,
+            "g1f3 g8f6 f3g1 f6g8 b1c3 b8c6 c3b1 c6b8 g1f3 g8f6 f3g1 f6g8 b1c3 b8c6 c3b1 c6b8 \
+            e2e4 e7e5 d2d4 e5d4 d1d4 b8c6 d4e3 g8f6 b1c3 f8b4 c1d2 e8g8 e1c1 f8e8 f2f3 d7d5",
+        );
+        let evals = vec![Some(Eval { score: 0, depth: 1 }); moves.len()];
+        let pgn = write_game(&mut pos, &moves, &[("Result", "1/2-1/2")], &evals);
+
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+        assert!(movetext.lines().count() > 1);
+        assert!(movetext.lines().all(|line| line.len() < 80));
+
+        let games = read_games(&pgn);
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(game.moves, moves);
+        assert_eq!(game.result, "1/2-1/2");
+    }
+}
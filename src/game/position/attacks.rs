@@ -0,0 +1,44 @@
+use crate::{
+    bit_boards::set_bits,
+    game::{
+        board::{colors, pieces},
+        moves::piece_attacks,
+        position::Position,
+    },
+};
+
+/// The pieces of both colors attacking `sq`, only counting those in `occ`.
+pub(super) const fn attackers_to(pos: &Position, sq: usize, occ: u64) -> u64 {
+    // a pawn attacks the squares an enemy pawn would attack it from
+    let pawns = piece_attacks(pieces::BLACK_PAWN, sq, occ) & pos.pawn_occupancy(colors::WHITE)
+        | piece_attacks(pieces::WHITE_PAWN, sq, occ) & pos.pawn_occupancy(colors::BLACK);
+    let knights = piece_attacks(pieces::WHITE_KNIGHT, sq, occ)
+        & (pos.knight_occupancy(colors::WHITE) | pos.knight_occupancy(colors::BLACK));
+    let kings = piece_attacks(pieces::WHITE_KING, sq, occ)
+        & (pos.king_occupancy(colors::WHITE) | pos.king_occupancy(colors::BLACK));
+
+    (pawns | knights | kings | slider_attackers(pos, sq, occ)) & occ
+}
+
+/// The bishops, rooks and queens of both colors attacking `sq`. Once a piece is removed from
+/// `occ`, the sliders behind it are revealed.
+pub(super) const fn slider_attackers(pos: &Position, sq: usize, occ: u64) -> u64 {
+    let queens = pos.queen_occupancy(colors::WHITE) | pos.queen_occupancy(colors::BLACK);
+    let bishops = pos.bishop_occupancy(colors::WHITE) | pos.bishop_occupancy(colors::BLACK);
+    let rooks = pos.rook_occupancy(colors::WHITE) | pos.rook_occupancy(colors::BLACK);
+
+    piece_attacks(pieces::WHITE_BISHOP, sq, occ) & (bishops | queens)
+        | piece_attacks(pieces::WHITE_ROOK, sq, occ) & (rooks | queens)
+}
+
+/// The squares attacked by the pieces of a color, sliders moving through the squares missing
+/// from `occ`.
+pub(super) const fn attack_map(pos: &Position, color: usize, occ: u64) -> u64 {
+    let mut attacks = 0;
+
+    set_bits!(pos.color_occupancy(color), sq, {
+        attacks |= piece_attacks(pos.get_piece(sq), sq, occ);
+    });
+
+    attacks
+}
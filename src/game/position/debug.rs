@@ -32,10 +32,11 @@ pub(crate) fn print_position(pos: &super::Position) {
     }
 
     println!(
-        " ({} {} {} {})\n",
+        " ({} {} {} {} {})\n",
         colors::initial_of(pos.active_color),
         super::fen::stringify_castling_rights(pos.castling_rights),
         super::fen::stringify_ep_square(pos.en_passant_sq),
-        pos.half_move_clock
+        pos.half_move_clock,
+        pos.full_move_number
     );
 }
@@ -17,8 +17,10 @@ pub(super) fn parse_fen(fen: &str) -> Result<ParsedFEN, FENError> {
         let castling_rights = parse_castling_rights(parts[2])?;
         let ep_sq = parse_ep_square(parts[3])?;
         let half_move_clock = parse_half_move_clock(parts[4])?;
+        let full_move_number = parse_full_move_number(parts[5])?;
 
-        return Ok((board, active_color, castling_rights, ep_sq, half_move_clock));
+        #[rustfmt::skip]
+        return Ok((board, active_color, castling_rights, ep_sq, half_move_clock, full_move_number));
     }
 
     Err(FENError::InvalidFormat(fen.to_owned()))
@@ -30,20 +32,27 @@ pub(super) fn create_fen(
     castling_rights: u8,
     ep_sq: usize,
     half_move_clock: u8,
+    full_move_number: u16,
 ) -> String {
     let b = stringify_board(board);
     let clr = colors::initial_of(active_color);
     let cr = stringify_castling_rights(castling_rights);
     let ep = stringify_ep_square(ep_sq);
     let hmc = half_move_clock;
+    let fmn = full_move_number;
 
-    format!("{} {} {} {} {}", b, clr, cr, ep, hmc)
+    format!("{} {} {} {} {} {}", b, clr, cr, ep, hmc, fmn)
 }
 
 fn parse_board(str: &str) -> Result<Board, FENError> {
     let mut board = [pieces::NONE; board::NB_SQUARES];
+    let rows = str.split("/").collect::<Vec<&str>>();
 
-    for (i, row) in str.split("/").enumerate() {
+    if rows.len() != board::NB_RANKS {
+        return Err(FENError::InvalidRankCount(rows.len()));
+    }
+
+    for (i, row) in rows.iter().enumerate() {
         let rank = squares::rev_coord(i);
         let mut file = lines::FILE_A;
 
@@ -52,17 +61,42 @@ fn parse_board(str: &str) -> Result<Board, FENError> {
 
             if piece == pieces::NONE {
                 match ch.to_digit(10) {
-                    Some(empty) => {
+                    Some(empty @ 1..=8) => {
                         file += empty as usize;
                         continue;
                     }
-                    None => return Err(FENError::InvalidBoard(str.to_owned(), ch)),
+                    _ => return Err(FENError::InvalidBoard(str.to_owned(), ch)),
                 };
             }
 
+            if file >= board::NB_FILES {
+                return Err(FENError::InvalidRankLength(row.to_string()));
+            }
+
+            if pieces::is_pawn(piece) && (rank == lines::RANK_1 || rank == lines::RANK_8) {
+                let sq = squares::of(rank, file);
+                return Err(FENError::PawnOnBackRank(squares::name_of(sq)));
+            }
+
             board[squares::of(rank, file)] = piece;
             file += 1;
         }
+
+        if file != board::NB_FILES {
+            return Err(FENError::InvalidRankLength(row.to_string()));
+        }
+    }
+
+    for color in 0..board::NB_COLORS {
+        let king = pieces::king_of(color);
+        let king_count = board.iter().filter(|&&piece| piece == king).count();
+
+        if king_count != 1 {
+            return Err(FENError::InvalidKingCount(
+                colors::initial_of(color),
+                king_count,
+            ));
+        }
     }
 
     Ok(board)
@@ -188,14 +222,31 @@ fn parse_half_move_clock(str: &str) -> Result<u8, FENError> {
     }
 }
 
-type ParsedFEN = (Board, usize, u8, usize, u8);
+fn parse_full_move_number(str: &str) -> Result<u16, FENError> {
+    match str.parse::<u16>() {
+        Ok(x) if x > 0 => Ok(x),
+        _ => Err(FENError::InvalidFullMoveNumber(str.to_owned())),
+    }
+}
+
+type ParsedFEN = (Board, usize, u8, usize, u8, u16);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum FENError {
     InvalidSquare(String),
     InvalidColor(String),
     InvalidBoard(String, char),
+    /// A rank that doesn't describe exactly 8 squares.
+    InvalidRankLength(String),
+    InvalidRankCount(usize),
+    /// The initial of the color and the number of kings it has.
+    InvalidKingCount(char, usize),
+    /// The square of a pawn on the first or last rank.
+    PawnOnBackRank(String),
+    /// The side that just moved left its king in check.
+    InactiveKingInCheck,
     InvalidCastlingRights(String),
     InvalidHalfMoveClock(String),
+    InvalidFullMoveNumber(String),
     InvalidFormat(String),
 }
@@ -1,5 +1,5 @@
 use crate::{
-    bit_boards::{bit_mask, is_bit_set, set_bits},
+    bit_boards::{bit_mask, is_bit_set},
     game::{
         board::{directions as dirs, pieces, squares},
         moves::{Move, castling, encoding, piece_attacks},
@@ -19,31 +19,14 @@ pub(crate) enum CheckType {
 
 impl CheckType {
     pub(crate) const fn get(pos: &Position, king_sq: usize) -> Self {
-        let full_occ = pos.full_occupancy();
-        let mut checker1: Option<(usize, usize)> = None;
-        let mut checker2 = false;
+        let checkers = pos.attackers_to(king_sq, pos.full_occupancy()) & pos.inactive_occupancy();
 
-        set_bits!(pos.inactive_occupancy(), sq, {
-            let piece = pos.get_piece(sq);
+        match checkers.count_ones() {
+            0 => Self::None,
+            1 => {
+                let sq = checkers.trailing_zeros() as usize;
 
-            if is_bit_set(piece_attacks(piece, sq, full_occ), king_sq) {
-                if checker1.is_none() {
-                    checker1 = Some((piece, sq));
-                    continue;
-                }
-
-                checker2 = true;
-                break;
-            }
-        });
-
-        if checker2 {
-            return Self::Double;
-        }
-
-        match checker1 {
-            Some((piece, sq)) => {
-                if pieces::is_slider(piece) {
+                if pieces::is_slider(pos.get_piece(sq)) {
                     let dir = dirs::get(king_sq, sq);
                     let ray = dirs::ray_of(king_sq, dir) & !dirs::ray_of(sq, dir);
                     Self::Single(ray)
@@ -51,7 +34,7 @@ impl CheckType {
                     Self::Single(bit_mask(sq))
                 }
             }
-            None => Self::None,
+            _ => Self::Double,
         }
     }
 
@@ -105,7 +88,9 @@ pub(crate) const fn gives_check(pos: &Position, enemy_king_sq: usize, mv: Move)
 
     // discovered check
     if let Some((checker, _, dir)) = find_next_piece(pos, enemy_king_sq, src_sq) {
-        return pieces::color_of(checker) == pos.active_color && can_pin(checker, dir);
+        return pieces::color_of(checker) == pos.active_color
+            && can_pin(checker, dir)
+            && dirs::get(enemy_king_sq, dest_sq) != dir;
     }
 
     false
@@ -0,0 +1,110 @@
+use crate::{
+    bit_boards::{bit_mask, is_bit_set},
+    game::{
+        board::{colors, pieces, squares},
+        moves::{Move, encoding, piece_attacks},
+        position::Position,
+    },
+    macros::ternary,
+};
+
+use super::{checks, pawn_moves};
+
+/// Checks that the pieces of the move are on the board and that the moving piece can reach its
+/// destination, whether or not the move leaves the king in check.
+pub(crate) const fn is_pseudo_legal(pos: &Position, mv: Move) -> bool {
+    let src_sq = encoding::src_square(mv);
+    let dest_sq = encoding::dest_square(mv);
+    let piece = encoding::src_piece(mv);
+    let captured = encoding::captured(mv);
+
+    if src_sq == dest_sq
+        || pos.get_piece(src_sq) != piece
+        || pieces::color_of(piece) != pos.active_color
+    {
+        return false;
+    }
+
+    if encoding::is_castling(mv) {
+        // rare enough to be checked against the generated moves
+        return pos.legal_moves().contains(mv);
+    }
+
+    if encoding::is_en_passant(mv) {
+        return pieces::is_pawn(piece)
+            && dest_sq == pos.en_passant_sq
+            && captured == pieces::rev_color(piece)
+            && is_bit_set(piece_attacks(piece, src_sq, 0), dest_sq);
+    }
+
+    if pos.get_piece(dest_sq) != captured
+        || captured != pieces::NONE
+            && (pieces::color_of(captured) == pos.active_color || pieces::is_king(captured))
+    {
+        return false;
+    }
+
+    if !pieces::is_pawn(piece) {
+        return !encoding::is_promotion(mv)
+            && is_bit_set(piece_attacks(piece, src_sq, pos.full_occupancy()), dest_sq);
+    }
+
+    if encoding::is_promotion(mv) != pawn_moves::is_promotion(dest_sq, pos.active_color) {
+        return false;
+    }
+
+    if encoding::is_promotion(mv) {
+        let promoted = encoding::promoted(mv);
+
+        if promoted >= pieces::NONE
+            || pieces::color_of(promoted) != pos.active_color
+            || pieces::is_pawn(promoted)
+            || pieces::is_king(promoted)
+        {
+            return false;
+        }
+    }
+
+    if captured != pieces::NONE {
+        return is_bit_set(piece_attacks(piece, src_sq, 0), dest_sq);
+    }
+
+    let push_sq = pawn_moves::push_dest_square(src_sq, pos.active_color);
+
+    dest_sq == push_sq
+        || squares::rank_of(src_sq) == colors::pawn_rank(pos.active_color)
+            && dest_sq == pawn_moves::push_dest_square(push_sq, pos.active_color)
+            && pos.get_piece(push_sq) == pieces::NONE
+}
+
+/// A pseudo-legal move is legal if it doesn't leave the king attacked. Its check flag must also
+/// be right, for the move to be equal to the generated one.
+pub(crate) const fn is_legal(pos: &Position, mv: Move) -> bool {
+    if !is_pseudo_legal(pos, mv)
+        || encoding::gives_check(mv)
+            != checks::gives_check(pos, pos.king_square(pos.inactive_color()), mv)
+    {
+        return false;
+    }
+
+    if encoding::is_castling(mv) {
+        return true;
+    }
+
+    let src_sq = encoding::src_square(mv);
+    let dest_sq = encoding::dest_square(mv);
+    let capture_sq = ternary!(
+        encoding::is_en_passant(mv),
+        squares::ep_capture_square(src_sq, dest_sq),
+        dest_sq
+    );
+    let king_sq = ternary!(
+        pieces::is_king(encoding::src_piece(mv)),
+        dest_sq,
+        pos.king_square(pos.active_color)
+    );
+    let occ = pos.full_occupancy() & !bit_mask(src_sq) & !bit_mask(capture_sq) | bit_mask(dest_sq);
+    let enemies = pos.inactive_occupancy() & !bit_mask(capture_sq);
+
+    pos.attackers_to(king_sq, occ) & enemies == 0
+}
@@ -1,6 +1,7 @@
 mod castling_moves;
 mod checks;
 mod figure_moves;
+mod legality;
 mod pawn_moves;
 mod pins;
 
@@ -8,13 +9,14 @@ use crate::{
     bit_boards::{bit_mask, set_bits},
     game::{
         board::pieces::{self, piece_types},
-        moves::{Move, MoveList, encoding, piece_attacks},
-        position::Position,
+        moves::{Move, MoveList, encoding},
+        position::{Position, attacks},
     },
 };
 
 use castling_moves::castling_moves;
 use figure_moves::figure_moves;
+pub(crate) use legality::{is_legal, is_pseudo_legal};
 use pawn_moves::pawn_moves;
 use pins::get_pin_mask;
 
@@ -64,12 +66,5 @@ const fn add_move(pos: &Position, moves: &mut MoveList, mut mv: Move) {
 
 /// Returns not only the attacked squares but also those X-rayed through the opposing king.
 const fn color_attacks(pos: &Position, color: usize, king_sq: usize) -> u64 {
-    let occ = pos.full_occupancy() & !bit_mask(king_sq);
-    let mut attacks = 0;
-
-    set_bits!(pos.color_occupancy(color), sq, {
-        attacks |= piece_attacks(pos.board[sq], sq, occ);
-    });
-
-    attacks
+    attacks::attack_map(pos, color, pos.full_occupancy() & !bit_mask(king_sq))
 }
@@ -1,5 +1,5 @@
 use crate::{
-    bit_boards::{is_bit_set, set_bits},
+    bit_boards::{bit_mask, is_bit_set, set_bits},
     game::{
         board::{NB_COLORS, colors, directions as dirs, pieces, squares},
         moves::{MoveList, encoding, piece_attacks},
@@ -58,7 +58,7 @@ const fn add_pawn_pushes(
     }
 }
 
-const fn push_dest_square(sq: usize, color: usize) -> usize {
+pub(super) const fn push_dest_square(sq: usize, color: usize) -> usize {
     const DIRECTIONS: [usize; NB_COLORS] = [dirs::NORTH, dirs::SOUTH];
 
     dirs::next_square(sq, DIRECTIONS[color])
@@ -71,9 +71,13 @@ const fn add_pawn_captures(
     pawn: usize,
     src_sq: usize,
 ) {
-    let bb = piece_attacks(pawn, src_sq, 0) & pin_check_mask;
+    let attacks = piece_attacks(pawn, src_sq, 0);
+    let bb = attacks & pin_check_mask;
 
-    if pos.en_passant_sq != squares::NONE && is_bit_set(bb, pos.en_passant_sq) {
+    if pos.en_passant_sq != squares::NONE
+        && is_bit_set(attacks, pos.en_passant_sq)
+        && is_en_passant_legal(pos, src_sq, pos.en_passant_sq)
+    {
         let captured = pieces::rev_color(pawn);
         super::add_move(
             pos,
@@ -98,7 +102,23 @@ const fn add_pawn_captures(
     });
 }
 
-const fn is_promotion(dest_sq: usize, color: usize) -> bool {
+/// En passant removes two pieces from the same rank, which may expose the king in ways the pin
+/// and check masks don't account for, so the resulting position is checked directly.
+const fn is_en_passant_legal(pos: &Position, src_sq: usize, dest_sq: usize) -> bool {
+    let capture_sq = squares::ep_capture_square(src_sq, dest_sq);
+    let king_sq = pos.king_square(pos.active_color);
+    let occ = pos.full_occupancy() & !bit_mask(src_sq) & !bit_mask(capture_sq) | bit_mask(dest_sq);
+
+    set_bits!(pos.inactive_occupancy() & !bit_mask(capture_sq), sq, {
+        if is_bit_set(piece_attacks(pos.get_piece(sq), sq, occ), king_sq) {
+            return false;
+        }
+    });
+
+    true
+}
+
+pub(super) const fn is_promotion(dest_sq: usize, color: usize) -> bool {
     squares::rank_of(dest_sq) == colors::piece_rank(colors::rev(color))
 }
 
@@ -22,7 +22,7 @@ pub(crate) const fn get_pin_mask(
     u64::MAX
 }
 
-/// If two squares are orthogonally or diagonally aligned,
+/// If two squares are orthogonally or diagonally aligned with nothing in between,
 /// returns the next piece along the same direction, its square and the direction.
 pub(crate) const fn find_next_piece(
     pos: &Position,
@@ -32,6 +32,12 @@ pub(crate) const fn find_next_piece(
     let dir = dirs::get(sq1, sq2);
 
     if dir != dirs::NONE {
+        let between = dirs::ray_of(sq1, dir) & !dirs::ray_of(sq2, dir) & !(1 << sq2);
+
+        if pos.full_occupancy() & between != 0 {
+            return None;
+        }
+
         let ray_occ = pos.full_occupancy() & dirs::ray_of(sq2, dir);
 
         if ray_occ != 0 {
@@ -1,9 +1,12 @@
+mod attacks;
 pub(crate) mod debug;
 mod fen;
 mod gen_moves;
 mod hashes;
+mod perft;
 mod play_move;
 mod repetitions;
+mod see;
 mod undo_info;
 
 #[cfg(test)]
@@ -11,15 +14,23 @@ mod tests;
 
 use crate::{
     bit_boards::{clear_bit, set_bit},
+    engine::Score,
     game::{
-        board::{Board, NB_COLORS, NB_PIECE_TYPES, NB_PIECES, NB_SQUARES, colors, pieces, squares},
-        moves::{Move, MoveList, castling::castling_color_mask, piece_attacks},
+        board::{Board, NB_COLORS, NB_PIECES, NB_SQUARES, colors, pieces, squares},
+        moves::{
+            Move, MoveList,
+            castling::castling_color_mask,
+            uci::{self, UCIMoveError},
+        },
     },
-    macros::const_while,
 };
 
 use self::repetitions as reps;
 
+pub(crate) use fen::FENError;
+pub(crate) use undo_info::UndoInfo;
+
+#[derive(Clone)]
 pub(crate) struct Position {
     board: Board,
     piece_occupancies: [u64; NB_PIECES],
@@ -28,6 +39,7 @@ pub(crate) struct Position {
     castling_rights: u8,
     en_passant_sq: usize,
     half_move_clock: u8,
+    full_move_number: u16,
     hash: u64,
     rep_table: reps::Table,
 }
@@ -35,14 +47,15 @@ pub(crate) struct Position {
 impl Position {
     pub(crate) const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
-    pub(crate) fn from_fen(fen: &str) -> Result<Self, fen::FENError> {
+    pub(crate) fn from_fen(fen: &str) -> Result<Self, FENError> {
         #[rustfmt::skip]
         let (
             board,
             active_color,
             castling_rights,
             en_passant_sq,
-            half_move_clock
+            half_move_clock,
+            full_move_number
         ) = fen::parse_fen(fen)?;
 
         let mut pos = Self {
@@ -51,6 +64,7 @@ impl Position {
             castling_rights,
             en_passant_sq,
             half_move_clock,
+            full_move_number,
             piece_occupancies: [0; NB_PIECES],
             color_occupancies: [0; NB_COLORS],
             rep_table: reps::create(),
@@ -68,6 +82,14 @@ impl Position {
         pos.hash ^= hashes::en_passant(en_passant_sq);
         reps::increment(&mut pos.rep_table, pos.hash);
 
+        pos.play_null_move();
+        let is_inactive_king_in_check = pos.is_check();
+        pos.undo_null_move(en_passant_sq);
+
+        if is_inactive_king_in_check {
+            return Err(FENError::InactiveKingInCheck);
+        }
+
         Ok(pos)
     }
 
@@ -78,8 +100,36 @@ impl Position {
             self.castling_rights,
             self.en_passant_sq,
             self.half_move_clock,
+            self.full_move_number,
         )
     }
+
+    /// Replaces the pieces and the side to move, clearing the castling rights and the en passant
+    /// square, without allocating a new repetition table.
+    /// Returns `false` if the inactive king is in check.
+    pub(crate) fn set_pieces(&mut self, pieces: &[(usize, usize)], active_color: usize) -> bool {
+        let mut occupancy = self.full_occupancy();
+
+        while occupancy != 0 {
+            self.remove_piece(occupancy.trailing_zeros() as usize);
+            occupancy &= occupancy - 1;
+        }
+
+        for &(piece, sq) in pieces {
+            self.set_piece(sq, piece);
+        }
+
+        self.set_active_color(active_color);
+        self.set_castling_rights(0);
+        self.set_ep_square(squares::NONE);
+        self.half_move_clock = 0;
+
+        self.play_null_move();
+        let is_inactive_king_in_check = self.is_check();
+        self.undo_null_move(squares::NONE);
+
+        !is_inactive_king_in_check
+    }
 }
 
 impl Position {
@@ -133,6 +183,11 @@ impl Position {
         self.half_move_clock
     }
 
+    /// Starts at 1 and is incremented after each move of Black.
+    pub(crate) const fn full_move_number(&self) -> u16 {
+        self.full_move_number
+    }
+
     pub(crate) const fn hash(&self) -> u64 {
         self.hash
     }
@@ -231,32 +286,63 @@ impl Position {
     }
 
     pub(crate) const fn is_check(&self) -> bool {
-        let color = self.active_color;
-        let enemy_color = self.inactive_color();
-        let king_sq = self.king_square(color);
-
-        if piece_attacks(pieces::pawn_of(color), king_sq, 0) & self.pawn_occupancy(enemy_color) != 0
-        {
-            return true;
-        }
-
-        let full_occ = self.full_occupancy();
+        self.is_square_attacked(self.king_square(self.active_color), self.inactive_color())
+    }
 
-        const_while!(piece_type, 1, NB_PIECE_TYPES, {
-            let piece = pieces::of(piece_type, enemy_color);
+    /// The pieces of both colors attacking `sq`, as if only the pieces in `occ` were on the
+    /// board. Removing pieces from `occ` reveals the sliders behind them.
+    pub(crate) const fn attackers_to(&self, sq: usize, occ: u64) -> u64 {
+        attacks::attackers_to(self, sq, occ)
+    }
 
-            if piece_attacks(piece, king_sq, full_occ) & self.piece_occupancy(piece) != 0 {
-                return true;
-            }
-        });
+    pub(crate) const fn is_square_attacked(&self, sq: usize, by_color: usize) -> bool {
+        self.attackers_to(sq, self.full_occupancy()) & self.color_occupancy(by_color) != 0
+    }
 
-        false
+    /// The squares attacked by the pieces of a color, whether they're occupied or not.
+    pub(crate) const fn attack_map(&self, color: usize) -> u64 {
+        attacks::attack_map(self, color, self.full_occupancy())
     }
 
     pub(crate) const fn legal_moves(&self) -> MoveList {
         gen_moves::legal_moves(self)
     }
 
+    /// Whether `mv` could be played here if it didn't leave the king in check. Moves taken from
+    /// the transposition table or the killer moves may belong to another position.
+    pub(crate) const fn is_pseudo_legal(&self, mv: Move) -> bool {
+        gen_moves::is_pseudo_legal(self, mv)
+    }
+
+    /// Whether `mv` is one of the legal moves, without generating them.
+    pub(crate) const fn is_legal(&self, mv: Move) -> bool {
+        gen_moves::is_legal(self, mv)
+    }
+
+    /// Finds the legal move written in long algebraic notation, e.g. `e2e4` or `e7e8q`.
+    pub(crate) fn parse_uci_move(&self, str: &str) -> Result<Move, UCIMoveError> {
+        uci::parse_uci(self, str)
+    }
+
+    /// The material won by `mv`, or lost if negative, once every capture on its destination
+    /// square worth making has been made. Pins are ignored.
+    pub(crate) fn see(&self, mv: Move) -> Score {
+        see::see(self, mv)
+    }
+
+    /// Whether `mv` wins at least `threshold` in the exchange started on its destination square.
+    pub(crate) fn see_ge(&self, mv: Move, threshold: Score) -> bool {
+        see::see_ge(self, mv, threshold)
+    }
+
+    pub(crate) fn perft(&mut self, depth: usize) -> u64 {
+        perft::perft(self, depth)
+    }
+
+    pub(crate) fn divide(&mut self, depth: usize) -> Vec<(Move, u64)> {
+        perft::divide(self, depth)
+    }
+
     pub(crate) const fn play_move(&mut self, mv: Move) {
         play_move::play_move(self, mv);
         play_move::update_info(self, mv);
@@ -269,6 +355,11 @@ impl Position {
         play_move::undo_move(self, mv);
         self.toggle_active_color();
         self.set_castling_rights(undo_info::castling_rights(undo_info));
+
+        if self.active_color == colors::BLACK {
+            self.full_move_number -= 1;
+        }
+
         self.set_ep_square(undo_info::ep_square(undo_info));
         self.half_move_clock = undo_info::half_move_clock(undo_info);
     }
@@ -0,0 +1,44 @@
+use crate::game::{moves::Move, position::Position};
+
+/// Counts the leaf nodes of the game tree at a given depth.
+/// Moves at the last ply are counted in bulk without being played.
+pub(super) fn perft(pos: &mut Position, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = pos.legal_moves();
+
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let undo_info = pos.undo_info();
+    let mut nodes = 0;
+
+    for &mv in &moves {
+        pos.play_move(mv);
+        nodes += perft(pos, depth - 1);
+        pos.undo_move(mv, undo_info);
+    }
+
+    nodes
+}
+
+/// Splits the perft count between the legal moves of the position.
+pub(super) fn divide(pos: &mut Position, depth: usize) -> Vec<(Move, u64)> {
+    let undo_info = pos.undo_info();
+    let mut result = Vec::new();
+
+    if depth == 0 {
+        return result;
+    }
+
+    for &mv in &pos.legal_moves() {
+        pos.play_move(mv);
+        result.push((mv, perft(pos, depth - 1)));
+        pos.undo_move(mv, undo_info);
+    }
+
+    result
+}
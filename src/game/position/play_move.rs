@@ -1,7 +1,7 @@
 use crate::{
     game::{
         board::{
-            NB_WINGS, pieces,
+            NB_WINGS, colors, pieces,
             squares::{self, ep_capture_square},
         },
         moves::{
@@ -80,6 +80,10 @@ pub(super) const fn update_info(pos: &mut Position, mv: Move) {
         pos.half_move_clock + 1
     );
 
+    if pos.active_color == colors::BLACK {
+        pos.full_move_number += 1;
+    }
+
     pos.toggle_active_color();
 }
 
@@ -0,0 +1,149 @@
+use crate::{
+    engine::{Score, piece_value},
+    game::{
+        board::{
+            colors,
+            pieces::{self, piece_types},
+        },
+        moves::{Move, encoding},
+        position::{Position, attacks::slider_attackers},
+    },
+    macros::ternary,
+};
+
+/// Both sides keep capturing on the destination square with their least valuable piece, each
+/// side being free to stop when capturing would lose material.
+pub(super) fn see(pos: &Position, mv: Move) -> Score {
+    let sq = encoding::dest_square(mv);
+    let mut occ = occupancy_after(pos, mv);
+    let mut attackers = pos.attackers_to(sq, occ);
+    let mut gains = [0; 32];
+    let mut target_value = moved_value(mv);
+    let mut color = pos.get_active_color();
+    let mut depth = 0;
+
+    gains[0] = move_gain(mv);
+
+    while depth < gains.len() - 1 {
+        color = colors::rev(color);
+
+        let Some(attacker_sq) = least_valuable(pos, attackers & pos.color_occupancy(color)) else {
+            break;
+        };
+        let attacker = pos.get_piece(attacker_sq);
+
+        occ &= !(1 << attacker_sq);
+        attackers = (attackers | slider_attackers(pos, sq, occ)) & occ;
+
+        // a king may only capture an undefended piece
+        if pieces::is_king(attacker) && attackers & pos.color_occupancy(colors::rev(color)) != 0 {
+            break;
+        }
+
+        depth += 1;
+        gains[depth] = target_value - gains[depth - 1];
+        target_value = piece_value(attacker);
+    }
+
+    while depth > 0 {
+        gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+        depth -= 1;
+    }
+
+    gains[0]
+}
+
+/// The same exchange, given up as soon as its outcome is known to be above or below the
+/// threshold.
+pub(super) fn see_ge(pos: &Position, mv: Move, threshold: Score) -> bool {
+    let sq = encoding::dest_square(mv);
+    // what the side to move still needs once the last capture is taken back
+    let mut swap = move_gain(mv) - threshold;
+
+    if swap < 0 {
+        return false;
+    }
+
+    swap = moved_value(mv) - swap;
+
+    if swap <= 0 {
+        return true;
+    }
+
+    let mut occ = occupancy_after(pos, mv);
+    let mut attackers = pos.attackers_to(sq, occ);
+    let mut color = pos.get_active_color();
+    let mut is_reached = true;
+
+    loop {
+        color = colors::rev(color);
+        attackers &= occ;
+
+        let Some(attacker_sq) = least_valuable(pos, attackers & pos.color_occupancy(color)) else {
+            break;
+        };
+        let attacker = pos.get_piece(attacker_sq);
+        is_reached = !is_reached;
+
+        if pieces::is_king(attacker) {
+            let is_defended = attackers & pos.color_occupancy(colors::rev(color)) != 0;
+            return ternary!(is_defended, !is_reached, is_reached);
+        }
+
+        swap = piece_value(attacker) - swap;
+
+        if swap < is_reached as Score {
+            break;
+        }
+
+        occ &= !(1 << attacker_sq);
+        attackers |= slider_attackers(pos, sq, occ);
+    }
+
+    is_reached
+}
+
+/// The square of the least valuable piece of `attackers`.
+fn least_valuable(pos: &Position, attackers: u64) -> Option<usize> {
+    for piece_type in piece_types::PAWN..=piece_types::KING {
+        let pieces_of_type = pos.piece_occupancy2(piece_type, colors::WHITE)
+            | pos.piece_occupancy2(piece_type, colors::BLACK);
+
+        if attackers & pieces_of_type != 0 {
+            return Some((attackers & pieces_of_type).trailing_zeros() as usize);
+        }
+    }
+
+    None
+}
+
+/// The board once the piece has left its square and an en passant capture has been made.
+fn occupancy_after(pos: &Position, mv: Move) -> u64 {
+    let mut occ = pos.full_occupancy() & !(1 << encoding::src_square(mv));
+
+    if encoding::is_en_passant(mv) {
+        occ &= !(1 << (encoding::dest_square(mv) ^ 8));
+    }
+
+    occ
+}
+
+/// The captured piece, plus the promoted piece in exchange for the pawn.
+const fn move_gain(mv: Move) -> Score {
+    let mut gain = piece_value(encoding::captured(mv));
+
+    if encoding::is_promotion(mv) {
+        gain += piece_value(encoding::promoted(mv)) - piece_value(encoding::src_piece(mv));
+    }
+
+    gain
+}
+
+/// The value of the piece that stands on the destination square after the move.
+const fn moved_value(mv: Move) -> Score {
+    ternary!(
+        encoding::is_promotion(mv),
+        piece_value(encoding::promoted(mv)),
+        piece_value(encoding::src_piece(mv))
+    )
+}
@@ -0,0 +1,61 @@
+use crate::{
+    bit_boards::bit_mask,
+    game::board::{colors, squares},
+};
+
+fn mask_of(squares: &[usize]) -> u64 {
+    squares.iter().fold(0, |mask, &sq| mask | bit_mask(sq))
+}
+
+#[test]
+fn attackers_to_square() {
+    let pos = super::from_fen("3r2k1/8/4p3/1N1p4/2P5/8/3Q4/3RK3 w - - 0 1");
+    let occ = pos.full_occupancy();
+
+    assert_eq!(
+        pos.attackers_to(squares::D5, occ),
+        mask_of(&[squares::C4, squares::D2, squares::E6, squares::D8])
+    );
+    // the rook behind the queen
+    assert_eq!(
+        pos.attackers_to(squares::D5, occ & !bit_mask(squares::D2)),
+        mask_of(&[squares::C4, squares::D1, squares::E6, squares::D8])
+    );
+    assert_eq!(
+        pos.attackers_to(squares::D6, occ),
+        mask_of(&[squares::B5, squares::D8])
+    );
+}
+
+#[test]
+fn attacked_squares() {
+    let pos = super::from_fen("4k3/8/8/8/8/8/5p2/R3K3 w - - 0 1");
+
+    assert!(pos.is_square_attacked(squares::E1, colors::BLACK));
+    assert!(pos.is_square_attacked(squares::G1, colors::BLACK));
+    assert!(!pos.is_square_attacked(squares::F1, colors::BLACK));
+    assert!(pos.is_square_attacked(squares::A8, colors::WHITE));
+    assert!(!pos.is_square_attacked(squares::E8, colors::WHITE));
+    // the king blocks the rook
+    assert!(!pos.is_square_attacked(squares::G1, colors::WHITE));
+}
+
+#[test]
+fn attack_maps() {
+    let pos = super::from_fen("7k/8/8/8/8/8/8/N6K w - - 0 1");
+
+    assert_eq!(
+        pos.attack_map(colors::WHITE),
+        mask_of(&[
+            squares::B3,
+            squares::C2,
+            squares::G1,
+            squares::G2,
+            squares::H2
+        ])
+    );
+    assert_eq!(
+        pos.attack_map(colors::BLACK),
+        mask_of(&[squares::G8, squares::G7, squares::H7])
+    );
+}
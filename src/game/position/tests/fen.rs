@@ -0,0 +1,101 @@
+use crate::game::position::{FENError, Position};
+
+fn assert_fen_error(fen: &str, error: FENError) {
+    assert_eq!(Position::from_fen(fen).err(), Some(error));
+}
+
+#[test]
+fn round_trip() {
+    for fen in [
+        Position::START_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 37 112",
+    ] {
+        assert_eq!(super::from_fen(fen).to_fen(), fen);
+    }
+}
+
+#[test]
+fn full_move_number() {
+    let mut pos = super::from_fen(Position::START_FEN);
+    let mut history = Vec::new();
+
+    for str in ["e2e4", "e7e5", "g1f3"] {
+        let mv = pos.parse_uci_move(str).unwrap();
+        history.push((mv, pos.undo_info()));
+        pos.play_move(mv);
+    }
+
+    assert_eq!(pos.full_move_number(), 2);
+
+    while let Some((mv, undo_info)) = history.pop() {
+        pos.undo_move(mv, undo_info);
+    }
+
+    assert_eq!(pos.to_fen(), Position::START_FEN);
+}
+
+#[test]
+fn invalid_board() {
+    assert_fen_error(
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+        FENError::InvalidRankCount(7),
+    );
+    assert_fen_error(
+        "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        FENError::InvalidBoard(
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR".to_string(),
+            '9',
+        ),
+    );
+    assert_fen_error(
+        "rnbqkbnr/pppppppp/7/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        FENError::InvalidRankLength("7".to_string()),
+    );
+    assert_fen_error(
+        "rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        FENError::InvalidRankLength("ppppppppp".to_string()),
+    );
+}
+
+#[test]
+fn illegal_position() {
+    assert_fen_error(
+        "8/8/8/8/8/8/8/K7 w - - 0 1",
+        FENError::InvalidKingCount('b', 0),
+    );
+    assert_fen_error(
+        "k7/8/8/8/8/8/8/K6K w - - 0 1",
+        FENError::InvalidKingCount('w', 2),
+    );
+    assert_fen_error(
+        "k7/8/8/8/8/8/8/K3p3 w - - 0 1",
+        FENError::PawnOnBackRank("e1".to_string()),
+    );
+    assert_fen_error(
+        "P3k3/8/8/8/8/8/8/K7 w - - 0 1",
+        FENError::PawnOnBackRank("a8".to_string()),
+    );
+    assert_fen_error(
+        "k6R/8/8/8/8/8/8/K7 w - - 0 1",
+        FENError::InactiveKingInCheck,
+    );
+    assert!(Position::from_fen("k6R/8/8/8/8/8/8/K7 b - - 0 1").is_ok());
+}
+
+#[test]
+fn invalid_fields() {
+    assert_fen_error(
+        "k7/8/8/8/8/8/8/K7 w - - 0",
+        FENError::InvalidFormat("k7/8/8/8/8/8/8/K7 w - - 0".to_string()),
+    );
+    assert_fen_error(
+        "k7/8/8/8/8/8/8/K7 w - - 0 0",
+        FENError::InvalidFullMoveNumber("0".to_string()),
+    );
+    assert_fen_error(
+        "k7/8/8/8/8/8/8/K7 w - - x 1",
+        FENError::InvalidHalfMoveClock("x".to_string()),
+    );
+}
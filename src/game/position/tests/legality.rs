@@ -0,0 +1,72 @@
+use crate::game::moves::{Move, NULL_MOVE, encoding};
+
+const FENS: [&str; 10] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    // en passant, once legal, once pinned
+    "4k3/8/8/2KPp2r/8/8/8/8 w - e6 0 1",
+    "4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1",
+    // check and double check
+    "4k3/8/8/8/1b6/8/3P4/4K2R w K - 0 1",
+    "k7/5P2/6P1/3K4/8/1R2n2Q/5N2/7b w - - 0 1",
+];
+
+#[test]
+fn generated_moves_are_legal() {
+    for fen in FENS {
+        let pos = super::from_fen(fen);
+
+        for &mv in &pos.legal_moves() {
+            assert!(pos.is_legal(mv), "{} {}", fen, encoding::src_square(mv));
+        }
+    }
+}
+
+/// Moves of every other position are only legal if they're generated in this one too.
+#[test]
+fn foreign_moves() {
+    let moves = FENS
+        .iter()
+        .flat_map(|fen| super::from_fen(fen).legal_moves().as_slice().to_vec())
+        .collect::<Vec<Move>>();
+
+    for fen in FENS {
+        let pos = super::from_fen(fen);
+        let legal_moves = pos.legal_moves();
+
+        for &mv in &moves {
+            assert_eq!(pos.is_legal(mv), legal_moves.contains(mv), "{}", fen);
+        }
+
+        assert!(!pos.is_legal(NULL_MOVE));
+    }
+}
+
+#[test]
+fn pseudo_legal_moves() {
+    let pos = super::from_fen("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1");
+    let pinned_mv = super::from_fen("k7/8/8/8/8/8/4R3/4K3 w - - 0 1")
+        .parse_uci_move("e2d2")
+        .unwrap();
+
+    assert!(pos.is_pseudo_legal(pinned_mv));
+    assert!(!pos.is_legal(pinned_mv));
+
+    let mv = pos.parse_uci_move("e2e7").unwrap();
+    let unmarked_mv = mv & !encoding::mark_check(NULL_MOVE);
+
+    assert!(pos.is_legal(mv));
+    assert!(pos.is_pseudo_legal(unmarked_mv));
+    assert!(!pos.is_legal(unmarked_mv));
+    // a pawn can't push onto a piece
+    let pos = super::from_fen("4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1");
+    let push = super::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1")
+        .parse_uci_move("e2e3")
+        .unwrap();
+
+    assert!(!pos.is_pseudo_legal(push));
+}
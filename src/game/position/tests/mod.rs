@@ -1,13 +1,18 @@
 use rand::{Rng, rng};
 
 use crate::game::{
-    board::{pieces, squares},
+    board::{colors, pieces, squares},
     moves::{Move, MoveList, encoding},
     position::{Position, undo_info::UndoInfo},
 };
 
+mod attacks;
 mod castling;
+mod fen;
+mod legality;
 mod pawn_moves;
+mod perft;
+mod see;
 
 pub(self) fn filter_move_kind(pos: &mut Position, mv_kind: u32) -> MoveList {
     let mut moves = pos.legal_moves();
@@ -132,29 +137,35 @@ fn consistent_hash() {
 
 #[test]
 fn triple_repetition() {
-    use squares::{A3, B1, G8, H6};
-
     let mut pos = from_fen(Position::START_FEN);
-    let white_piece = pieces::WHITE_KNIGHT;
-    let black_piece = pieces::BLACK_KNIGHT;
-    let captured = pieces::NONE;
-
-    let white_mv1 = encoding::normal_move(B1, A3, white_piece, captured);
-    let white_mv2 = encoding::normal_move(A3, B1, white_piece, captured);
-    let black_mv1 = encoding::normal_move(G8, H6, black_piece, captured);
-    let black_mv2 = encoding::normal_move(H6, G8, black_piece, captured);
-
-    pos.play_move(white_mv1);
-    pos.play_move(black_mv1);
 
-    pos.play_move(white_mv2);
-    pos.play_move(black_mv2);
-
-    pos.play_move(white_mv1);
-    pos.play_move(black_mv1);
-
-    pos.play_move(white_mv2);
-    pos.play_move(black_mv2);
+    for _ in 0..2 {
+        for str in ["b1a3", "g8h6", "a3b1", "h6g8"] {
+            let mv = pos.parse_uci_move(str).unwrap();
+            pos.play_move(mv);
+        }
+    }
 
     assert_eq!(pos.rep_count(), 3);
 }
+
+#[test]
+fn replace_pieces() {
+    let mut pos = from_fen(Position::START_FEN);
+    let pieces = [
+        (pieces::WHITE_KING, squares::E1),
+        (pieces::BLACK_KING, squares::E8),
+        (pieces::WHITE_ROOK, squares::A7),
+    ];
+
+    assert!(pos.set_pieces(&pieces, colors::BLACK));
+    assert_eq!(pos.to_fen(), "4k3/R7/8/8/8/8/8/4K3 b - - 0 1");
+    assert_eq!(pos.hash, from_fen("4k3/R7/8/8/8/8/8/4K3 b - - 0 1").hash);
+
+    let pieces = [
+        (pieces::WHITE_KING, squares::E1),
+        (pieces::BLACK_KING, squares::E8),
+        (pieces::WHITE_ROOK, squares::E7),
+    ];
+    assert!(!pos.set_pieces(&pieces, colors::WHITE));
+}
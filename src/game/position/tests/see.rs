@@ -0,0 +1,61 @@
+use crate::engine::Score;
+
+/// Checks `see` along with `see_ge` at and just above its value.
+fn assert_see(fen: &str, uci: &str, expected: Score) {
+    let pos = super::from_fen(fen);
+    let mv = pos.parse_uci_move(uci).unwrap();
+
+    assert_eq!(pos.see(mv), expected, "{} {}", fen, uci);
+    assert!(pos.see_ge(mv, expected), "{} {}", fen, uci);
+    assert!(!pos.see_ge(mv, expected + 1), "{} {}", fen, uci);
+}
+
+#[test]
+fn undefended_pieces() {
+    assert_see("4k3/8/8/3p4/8/8/8/3RK3 w - - 0 1", "d1d5", 1000);
+    assert_see("4k3/8/8/3r4/4P3/8/8/3QK3 w - - 0 1", "e4d5", 5000);
+    assert_see("4k3/8/8/8/8/8/8/3RK3 w - - 0 1", "d1d5", 0);
+}
+
+#[test]
+fn defended_pieces() {
+    assert_see("4k3/8/4p3/3p4/8/8/8/3RK3 w - - 0 1", "d1d5", -4000);
+    // a pawn takes a defended knight
+    assert_see("4k3/8/4p3/3n4/4P3/8/8/4K3 w - - 0 1", "e4d5", 2000);
+    // the queen ends up taken by the rook
+    assert_see(
+        "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+        "e1e5",
+        1000,
+    );
+    assert_see(
+        "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+        "d3e5",
+        -2000,
+    );
+}
+
+#[test]
+fn x_rays() {
+    // the queen behind the rook recaptures
+    assert_see("3rk3/8/8/3p4/8/8/3R4/3QK3 w - - 0 1", "d2d5", 1000);
+    // and so does the rook behind the queen
+    assert_see("3rk3/3r4/8/3p4/8/8/3Q4/3RK3 w - - 0 1", "d2d5", -8500);
+    // the bishop behind the pawn
+    assert_see("4k3/8/2p5/3r4/4P3/5B2/8/4K3 w - - 0 1", "e4d5", 5000);
+}
+
+#[test]
+fn kings() {
+    // the king can't take a defended piece
+    assert_see("8/8/8/3pk3/8/8/3R4/3RK3 w - - 0 1", "d2d5", 1000);
+    assert_see("8/8/8/3pk3/8/8/8/3RK3 w - - 0 1", "d1d5", -4000);
+}
+
+#[test]
+fn special_moves() {
+    assert_see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 1000);
+    // the promoted queen is taken back
+    assert_see("3rk3/2P5/8/8/8/8/8/4K3 w - - 0 1", "c7c8q", -1000);
+    assert_see("3rk3/2P5/8/8/8/8/8/4K3 w - - 0 1", "c7d8q", 4000);
+}
@@ -1,7 +1,7 @@
 use crate::game::moves::castling::NB_BITS_CASTLING_RIGHTS;
 
 /// A number that stores information about a position that has to be restored after undoing a move.
-pub(super) type UndoInfo = u32;
+pub(crate) type UndoInfo = u32;
 
 const NB_BITS_CASTLING: UndoInfo = NB_BITS_CASTLING_RIGHTS as UndoInfo;
 const NB_BITS_EP: UndoInfo = 7;
@@ -2,12 +2,26 @@
 
 mod benchmarks;
 mod bit_boards;
+mod book;
+mod cli;
 mod engine;
 mod game;
 mod macros;
+mod protocols;
+mod retrograde;
+mod syzygy;
+mod test_suite;
 
 fn main() {
-    _test_positions();
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+
+    if args.is_empty() {
+        protocols::run();
+    } else {
+        cli::run(&args);
+    }
+
+    // _test_positions();
     // benchmarks::run();
 }
 
@@ -33,7 +47,7 @@ fn _test_positions() {
         print_position(&pos);
 
         macros::bench!({
-            engine::run(&mut pos, max_depth, true);
+            engine::run(&mut pos, &engine::Limits::depth(max_depth), true);
         });
 
         println!("- - - - - - - - - -\n");
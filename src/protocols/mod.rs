@@ -0,0 +1,24 @@
+pub(crate) mod uci;
+pub(crate) mod xboard;
+
+use std::io::{self, BufRead, Cursor, Read};
+
+/// Talks to a GUI or match runner over stdin and stdout.
+/// The protocol is picked from the first line received: `xboard` selects CECP, anything else UCI.
+pub(crate) fn run() {
+    let mut stdin = io::stdin().lock();
+    let mut first_line = String::new();
+
+    if stdin.read_line(&mut first_line).unwrap_or(0) == 0 {
+        return;
+    }
+
+    let is_xboard = first_line.trim() == "xboard";
+    let input = Cursor::new(first_line).chain(stdin);
+
+    if is_xboard {
+        xboard::run(input);
+    } else {
+        uci::run(input);
+    }
+}
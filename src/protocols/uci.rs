@@ -0,0 +1,559 @@
+use std::{
+    io::BufRead,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    book::{Book, Selection},
+    engine::{self, Context, Iteration, Limits, Score, SearchHandle},
+    game::{
+        board::colors,
+        moves::{Move, to_uci},
+        position::Position,
+    },
+    syzygy::Tablebases,
+};
+
+const ENGINE_NAME: &str = "chess";
+const ENGINE_AUTHOR: &str = "MelvDouc";
+
+const MAX_HASH_SIZE_MB: usize = 4096;
+const MAX_THREADS: usize = 256;
+const MAX_MULTI_PV: usize = 256;
+
+/// The best move of a running search. That of `go infinite` and `go ponder` is held back
+/// until `stop` or `ponderhit`, even if the search ends before.
+#[derive(Default)]
+struct BestMove {
+    held: bool,
+    /// Set when the search ends.
+    found: Option<Move>,
+    sent: bool,
+}
+
+impl BestMove {
+    /// Sends the move if the search is over and the move is no longer held back.
+    fn send(&mut self) -> Option<Move> {
+        let mv = self.found.filter(|_| !self.held && !self.sent)?;
+        self.sent = true;
+        println!("bestmove {}", to_uci(mv));
+        Some(mv)
+    }
+}
+
+struct Uci {
+    pos: Position,
+    hash_size_mb: usize,
+    threads: usize,
+    multi_pv: usize,
+    /// Kept between searches to reuse the transposition table, allocated on first use.
+    ctx: Option<Context>,
+    search: Option<SearchHandle>,
+    /// Shared with the search thread, which sends the move when it isn't held back.
+    best_move: Arc<Mutex<BestMove>>,
+    /// Consulted before searching when `own_book` is set.
+    book: Option<Book>,
+    own_book: bool,
+    book_selection: Selection,
+    tablebases: Option<Arc<Tablebases>>,
+}
+
+/// Reads UCI commands until `quit` or the end of the input.
+pub(crate) fn run(input: impl BufRead) {
+    let mut uci = Uci {
+        pos: Position::from_fen(Position::START_FEN).unwrap(),
+        hash_size_mb: engine::DEFAULT_HASH_SIZE_MB,
+        threads: 1,
+        multi_pv: 1,
+        ctx: None,
+        search: None,
+        best_move: Arc::default(),
+        book: None,
+        own_book: true,
+        book_selection: Selection::WeightedRandom,
+        tablebases: None,
+    };
+
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        if !uci.handle_command(&line) {
+            break;
+        }
+    }
+
+    uci.stop_search();
+}
+
+impl Uci {
+    /// Returns `false` when the engine should quit.
+    fn handle_command(&mut self, line: &str) -> bool {
+        let tokens = line.split_whitespace().collect::<Vec<&str>>();
+
+        let Some((&command, args)) = tokens.split_first() else {
+            return true;
+        };
+
+        match command {
+            "uci" => {
+                println!("id name {}", ENGINE_NAME);
+                println!("id author {}", ENGINE_AUTHOR);
+                println!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    engine::DEFAULT_HASH_SIZE_MB,
+                    MAX_HASH_SIZE_MB
+                );
+                println!(
+                    "option name Threads type spin default 1 min 1 max {}",
+                    MAX_THREADS
+                );
+                println!(
+                    "option name MultiPV type spin default 1 min 1 max {}",
+                    MAX_MULTI_PV
+                );
+                println!("option name OwnBook type check default true");
+                println!("option name BookFile type string default <empty>");
+                println!("option name BookSelection type combo default Random var Random var Best");
+                println!("option name SyzygyPath type string default <empty>");
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "ucinewgame" => {
+                self.stop_search();
+                self.pos = Position::from_fen(Position::START_FEN).unwrap();
+                self.ctx = None;
+            }
+            "setoption" => self.set_option(args),
+            "position" => {
+                self.stop_search();
+
+                if let Err(message) = self.set_position(args) {
+                    println!("info string {}", message);
+                }
+            }
+            "go" => self.go(args),
+            "stop" => {
+                self.stop_search();
+            }
+            "ponderhit" => self.ponderhit(),
+            "quit" => return false,
+            _ => println!("info string unknown command: {}", command),
+        };
+
+        true
+    }
+
+    /// `setoption name <name> [value <value>]`
+    fn set_option(&mut self, args: &[&str]) {
+        let value_index = args.iter().position(|&arg| arg == "value");
+
+        if args.first() != Some(&"name") || value_index == Some(1) {
+            println!("info string malformed setoption: {}", args.join(" "));
+            return;
+        }
+
+        let name = args[1..value_index.unwrap_or(args.len())].join(" ");
+        let value = value_index.map(|i| args[i + 1..].join(" "));
+
+        match (name.to_lowercase().as_str(), value) {
+            ("hash", Some(value)) => match value.parse::<usize>() {
+                Ok(size) => {
+                    self.stop_search();
+                    self.hash_size_mb = size.clamp(1, MAX_HASH_SIZE_MB);
+                    self.ctx = None;
+                }
+                Err(_) => println!("info string invalid hash size: {}", value),
+            },
+            ("threads", Some(value)) => match value.parse::<usize>() {
+                Ok(threads) => self.threads = threads.clamp(1, MAX_THREADS),
+                Err(_) => println!("info string invalid thread count: {}", value),
+            },
+            ("multipv", Some(value)) => match value.parse::<usize>() {
+                Ok(multi_pv) => self.multi_pv = multi_pv.clamp(1, MAX_MULTI_PV),
+                Err(_) => println!("info string invalid MultiPV: {}", value),
+            },
+            ("ownbook", Some(value)) => self.own_book = value == "true",
+            ("bookfile", Some(value)) if value.is_empty() || value == "<empty>" => self.book = None,
+            ("bookfile", Some(path)) => match Book::open(&path) {
+                Ok(book) => self.book = Some(book),
+                Err(error) => println!("info string cannot open book {}: {}", path, error),
+            },
+            ("bookselection", Some(value)) => match value.to_lowercase().as_str() {
+                "best" => self.book_selection = Selection::BestWeight,
+                "random" => self.book_selection = Selection::WeightedRandom,
+                _ => println!("info string invalid book selection: {}", value),
+            },
+            ("syzygypath", Some(value)) if value.is_empty() || value == "<empty>" => {
+                self.tablebases = None
+            }
+            ("syzygypath", Some(paths)) => {
+                let tablebases = Tablebases::new(&paths);
+                println!(
+                    "info string found {} tablebases of up to {} pieces",
+                    tablebases.len(),
+                    tablebases.cardinality()
+                );
+                self.tablebases = Some(Arc::new(tablebases));
+            }
+            _ => println!("info string unknown option: {}", name),
+        };
+    }
+
+    /// `position (startpos | fen <fen>) [moves <move>...]`
+    fn set_position(&mut self, args: &[&str]) -> Result<(), String> {
+        let moves_index = args.iter().position(|&arg| arg == "moves");
+        let (setup, moves) = match moves_index {
+            Some(i) => (&args[..i], &args[i + 1..]),
+            None => (args, &args[args.len()..]),
+        };
+
+        let mut pos = match setup.split_first() {
+            Some((&"startpos", _)) => Position::from_fen(Position::START_FEN).unwrap(),
+            Some((&"fen", fen)) => match Position::from_fen(&fen.join(" ")) {
+                Ok(pos) => pos,
+                Err(error) => return Err(format!("invalid FEN: {:?}", error)),
+            },
+            _ => return Err("expected startpos or fen".to_string()),
+        };
+
+        for &str in moves {
+            match pos.parse_uci_move(str) {
+                Ok(mv) => pos.play_move(mv),
+                Err(_) => return Err(format!("illegal move: {}", str)),
+            };
+        }
+
+        self.pos = pos;
+        Ok(())
+    }
+
+    fn go(&mut self, args: &[&str]) {
+        self.stop_search();
+
+        // an infinite search is for analysis, which the book would cut short, as is a mate search,
+        // and pondering mustn't send a move before `ponderhit`
+        if !args.contains(&"infinite")
+            && !args.contains(&"mate")
+            && !args.contains(&"ponder")
+            && let Some(mv) = self.book_move()
+        {
+            println!("bestmove {}", to_uci(mv));
+            return;
+        }
+
+        let limits = parse_go(args, self.pos.get_active_color());
+        let hash_size_mb = self.hash_size_mb;
+        let mut ctx = self
+            .ctx
+            .take()
+            .unwrap_or_else(|| Context::new(hash_size_mb));
+        ctx.set_tablebases(self.tablebases.clone());
+        ctx.set_threads(self.threads);
+        ctx.set_multi_pv(self.multi_pv);
+        let best_move = Arc::new(Mutex::new(BestMove {
+            held: args.contains(&"infinite") || args.contains(&"ponder"),
+            ..BestMove::default()
+        }));
+        self.best_move = Arc::clone(&best_move);
+
+        self.search = Some(SearchHandle::spawn(
+            self.pos.clone(),
+            ctx,
+            limits,
+            print_info,
+            move |best_mv| {
+                let mut best_move = best_move.lock().unwrap();
+                best_move.found = Some(best_mv);
+                best_move.send();
+            },
+        ));
+    }
+
+    fn book_move(&self) -> Option<Move> {
+        match (self.own_book, &self.book) {
+            (true, Some(book)) => book.pick(&self.pos, self.book_selection),
+            _ => None,
+        }
+    }
+
+    /// Returns the best move if it was held back until now.
+    fn stop_search(&mut self) -> Option<Move> {
+        let search = self.search.take()?;
+        let (_, ctx) = search.stop_and_join();
+        self.ctx = Some(ctx);
+
+        let mut best_move = self.best_move.lock().unwrap();
+        best_move.held = false;
+        best_move.send()
+    }
+
+    /// The opponent played the move pondered on: the search goes on under its time limits,
+    /// counted from now, and sends its move when it ends.
+    fn ponderhit(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+
+        search.ponderhit();
+        let mut best_move = self.best_move.lock().unwrap();
+        best_move.held = false;
+        best_move.send();
+    }
+}
+
+/// `go [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>] [movestogo <n>] [movetime <ms>]
+/// [depth <plies>] [nodes <n>] [mate <moves>] [infinite] [ponder]`
+/// Without any limit, the search runs until `stop`. When pondering, the clock starts on `ponderhit`.
+fn parse_go(args: &[&str], color: usize) -> Limits {
+    let (time_arg, inc_arg) = match color {
+        colors::WHITE => ("wtime", "winc"),
+        _ => ("btime", "binc"),
+    };
+    let mut limits = Limits::depth(engine::MAX_DEPTH);
+    let mut i = 0;
+
+    while i < args.len() {
+        let value = args.get(i + 1).and_then(|arg| arg.parse::<u64>().ok());
+
+        match (args[i], value) {
+            (arg, Some(time)) if arg == time_arg => limits.time = Some(time),
+            (arg, Some(increment)) if arg == inc_arg => limits.increment = increment,
+            ("movestogo", Some(moves_to_go)) => limits.moves_to_go = Some(moves_to_go),
+            ("movetime", Some(move_time)) => limits.move_time = Some(move_time),
+            ("depth", Some(depth)) => limits.depth = depth as usize,
+            ("nodes", Some(nodes)) => limits.nodes = Some(nodes),
+            ("mate", Some(moves)) => limits.mate = Some(moves as usize),
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        i += 2;
+    }
+
+    limits.ponder = args.contains(&"ponder");
+    limits
+}
+
+/// One `info` line per PV, the best first.
+fn print_info(_: &mut Position, iteration: &Iteration) {
+    let millis = iteration.elapsed.as_millis().max(1);
+    let nps = iteration.nodes as u128 * 1000 / millis;
+
+    for (i, line) in iteration.lines.iter().enumerate() {
+        let pv = line
+            .pv
+            .iter()
+            .map(|&mv| to_uci(mv))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        println!(
+            "info depth {} multipv {} score {} nodes {} nps {} tbhits {} time {} pv {}",
+            iteration.depth,
+            i + 1,
+            stringify_score(line.score),
+            iteration.nodes,
+            nps,
+            iteration.tb_hits,
+            millis,
+            pv
+        );
+    }
+}
+
+/// `mate <moves>`, negative when the engine is getting mated, or `cp <centipawns>`.
+fn stringify_score(score: Score) -> String {
+    match engine::is_mate_score(score) {
+        true => format!("mate {}", engine::moves_to_mate(score)),
+        false => format!("cp {}", engine::to_centipawns(score)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::game::board::{pieces, squares};
+
+    use super::*;
+
+    fn create_uci() -> Uci {
+        Uci {
+            pos: Position::from_fen(Position::START_FEN).unwrap(),
+            hash_size_mb: 1,
+            threads: 1,
+            multi_pv: 1,
+            ctx: None,
+            search: None,
+            best_move: Arc::default(),
+            book: None,
+            own_book: true,
+            book_selection: Selection::WeightedRandom,
+            tablebases: None,
+        }
+    }
+
+    #[test]
+    fn position_with_moves() {
+        let mut uci = create_uci();
+        uci.set_position(&["startpos", "moves", "e2e4", "c7c5", "g1f3"])
+            .unwrap();
+
+        assert_eq!(
+            uci.pos.to_fen(),
+            "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+    }
+
+    #[test]
+    fn position_from_fen() {
+        let mut uci = create_uci();
+        let args = "fen 7k/P7/8/8/8/8/8/K7 w - - 0 1 moves a7a8n"
+            .split(' ')
+            .collect::<Vec<&str>>();
+        uci.set_position(&args).unwrap();
+
+        assert_eq!(uci.pos.get_piece(squares::A8), pieces::WHITE_KNIGHT);
+    }
+
+    #[test]
+    fn go_with_clocks() {
+        let args = "wtime 60000 btime 50000 winc 1000 binc 2000 movestogo 20"
+            .split(' ')
+            .collect::<Vec<&str>>();
+        let limits = parse_go(&args, colors::BLACK);
+
+        assert_eq!(limits.depth, engine::MAX_DEPTH);
+        assert_eq!(limits.time, Some(50_000));
+        assert_eq!(limits.increment, 2000);
+        assert_eq!(limits.moves_to_go, Some(20));
+        assert_eq!(limits.move_time, None);
+    }
+
+    #[test]
+    fn go_with_fixed_limits() {
+        let limits = parse_go(&["depth", "7", "nodes", "10000", "movetime", "500"], 0);
+
+        assert_eq!(limits.depth, 7);
+        assert_eq!(limits.nodes, Some(10_000));
+        assert_eq!(limits.move_time, Some(500));
+        assert_eq!(limits.time, None);
+        assert_eq!(parse_go(&["mate", "3"], 0).mate, Some(3));
+    }
+
+    #[test]
+    fn reject_illegal_move() {
+        let mut uci = create_uci();
+
+        assert!(uci.set_position(&["startpos", "moves", "e2e5"]).is_err());
+        assert_eq!(uci.pos.to_fen(), create_uci().pos.to_fen());
+    }
+
+    #[test]
+    fn infinite_search_waits_for_stop() {
+        let mut uci = create_uci();
+        let fen = "fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        uci.set_position(&fen.split(' ').collect::<Vec<&str>>())
+            .unwrap();
+
+        uci.go(&["depth", "1"]);
+        while !uci.search.as_ref().unwrap().is_finished() {}
+        // already sent
+        assert_eq!(uci.stop_search(), None);
+
+        uci.go(&["infinite", "depth", "2"]);
+        while !uci.search.as_ref().unwrap().is_finished() {}
+        assert_eq!(uci.stop_search().map(to_uci), Some("a1a8".to_string()));
+        assert_eq!(uci.stop_search(), None);
+    }
+
+    #[test]
+    fn ponderhit_starts_the_clock() {
+        let mut uci = create_uci();
+
+        uci.go(&["ponder", "wtime", "100", "btime", "100"]);
+        thread::sleep(Duration::from_millis(200));
+        assert!(!uci.search.as_ref().unwrap().is_finished());
+
+        uci.handle_command("ponderhit");
+        let search = uci.search.as_ref().unwrap();
+        let instant = Instant::now();
+
+        while !search.is_finished() {
+            assert!(instant.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        assert!(uci.best_move.lock().unwrap().sent);
+        // already sent
+        assert_eq!(uci.stop_search(), None);
+    }
+
+    #[test]
+    fn book_options() {
+        let mut uci = create_uci();
+
+        uci.set_option(&["name", "OwnBook", "value", "false"]);
+        uci.set_option(&["name", "BookSelection", "value", "Best"]);
+        uci.set_option(&["name", "BookFile", "value", "does/not/exist.bin"]);
+
+        assert!(!uci.own_book);
+        assert_eq!(uci.book_selection, Selection::BestWeight);
+        assert!(uci.book.is_none());
+    }
+
+    #[test]
+    fn search_options() {
+        let mut uci = create_uci();
+
+        uci.set_option(&["name", "Threads", "value", "4"]);
+        assert_eq!(uci.threads, 4);
+
+        uci.set_option(&["name", "Threads", "value", "0"]);
+        assert_eq!(uci.threads, 1);
+
+        uci.set_option(&["name", "Threads", "value", "many"]);
+        assert_eq!(uci.threads, 1);
+
+        uci.set_option(&["name", "MultiPV", "value", "3"]);
+        assert_eq!(uci.multi_pv, 3);
+    }
+
+    #[test]
+    fn malformed_options() {
+        let mut uci = create_uci();
+
+        uci.set_option(&[]);
+        uci.set_option(&["value"]);
+        uci.set_option(&["value", "5"]);
+        uci.set_option(&["name"]);
+        uci.set_option(&["name", "value", "5"]);
+        uci.set_option(&["Threads", "value", "4"]);
+
+        assert_eq!(uci.threads, 1);
+    }
+
+    #[test]
+    fn mate_scores() {
+        assert_eq!(stringify_score(1234), "cp 123");
+        assert_eq!(stringify_score(engine::MATE_SCORE - 5), "mate 3");
+        assert_eq!(stringify_score(-engine::MATE_SCORE + 4), "mate -2");
+    }
+
+    #[test]
+    fn syzygy_path() {
+        let mut uci = create_uci();
+
+        uci.set_option(&["name", "SyzygyPath", "value", "does/not/exist"]);
+        assert_eq!(uci.tablebases.as_ref().map(|tb| tb.len()), Some(0));
+
+        uci.set_option(&["name", "SyzygyPath", "value", "<empty>"]);
+        assert!(uci.tablebases.is_none());
+    }
+}
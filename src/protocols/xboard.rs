@@ -0,0 +1,509 @@
+use std::{
+    io::BufRead,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use crate::{
+    engine::{self, Context, Iteration, Limits, Score, SearchHandle},
+    game::{
+        board::colors,
+        moves::{Move, NULL_MOVE, to_uci},
+        position::{Position, UndoInfo},
+    },
+    macros::ternary,
+    syzygy::Tablebases,
+};
+
+const ENGINE_NAME: &str = "chess";
+
+const FEATURES: &str = "ping=1 setboard=1 usermove=1 playother=1 san=0 colors=0 sigint=0 sigterm=0 smp=1 egt=\"syzygy\"";
+
+struct RunningSearch {
+    handle: SearchHandle,
+    /// Raised by whoever gets to decide first what happens to the move:
+    /// the search thread when it sends it, the main thread when it discards it.
+    claimed: Arc<AtomicBool>,
+}
+
+/// Clock settings received from the GUI, in milliseconds.
+#[derive(Default)]
+struct Clocks {
+    /// 0 for a sudden death time control.
+    moves_per_session: u32,
+    base: u64,
+    increment: u64,
+    /// Fixed time per move set by `st`.
+    move_time: Option<u64>,
+    engine: u64,
+    opponent: u64,
+}
+
+struct XBoard {
+    pos: Position,
+    history: Vec<(Move, UndoInfo)>,
+    /// In force mode, moves are only recorded and the engine never thinks.
+    force: bool,
+    engine_color: usize,
+    /// Whether thinking output is printed.
+    post: bool,
+    max_depth: Option<usize>,
+    clocks: Clocks,
+    /// Set by `cores`.
+    threads: usize,
+    /// Kept between searches to reuse the transposition table, allocated on first use.
+    ctx: Option<Context>,
+    search: Option<RunningSearch>,
+    tablebases: Option<Arc<Tablebases>>,
+}
+
+/// Reads CECP commands until `quit` or the end of the input.
+pub(crate) fn run(input: impl BufRead) {
+    let mut xboard = XBoard::new();
+
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        if !xboard.handle_command(&line) {
+            break;
+        }
+    }
+
+    xboard.abort_search();
+}
+
+impl XBoard {
+    fn new() -> Self {
+        Self {
+            pos: Position::from_fen(Position::START_FEN).unwrap(),
+            history: Vec::new(),
+            force: false,
+            engine_color: colors::BLACK,
+            post: false,
+            max_depth: None,
+            clocks: Clocks::default(),
+            threads: 1,
+            ctx: None,
+            search: None,
+            tablebases: None,
+        }
+    }
+
+    /// Returns `false` when the engine should quit.
+    fn handle_command(&mut self, line: &str) -> bool {
+        let tokens = line.split_whitespace().collect::<Vec<&str>>();
+
+        let Some((&command, args)) = tokens.split_first() else {
+            return true;
+        };
+
+        // the GUI may already be replying to the move sent
+        if self
+            .search
+            .as_ref()
+            .is_some_and(|search| search.claimed.load(Ordering::SeqCst))
+        {
+            self.finish_search(false);
+        }
+
+        // commands that may arrive while the engine is thinking
+        match command {
+            "quit" => return false,
+            "?" => self.finish_search(true),
+            "new" | "force" | "undo" | "remove" | "setboard" | "result" => self.abort_search(),
+            "go" | "playother" | "usermove" | "level" | "st" | "sd" | "cores" | "egtpath" => {
+                self.finish_search(false)
+            }
+            _ if self.pos.parse_uci_move(command).is_ok() => self.finish_search(false),
+            _ => {}
+        };
+
+        match command {
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer"
+            | "name" | "rating" | "result" | "?" => {}
+            "protover" => {
+                println!("feature myname=\"{}\" {}", ENGINE_NAME, FEATURES);
+                println!("feature done=1");
+            }
+            "new" => {
+                *self = Self {
+                    post: self.post,
+                    threads: self.threads,
+                    ctx: self.ctx.take(),
+                    tablebases: self.tablebases.take(),
+                    ..Self::new()
+                }
+            }
+            "force" => self.force = true,
+            "go" => {
+                self.force = false;
+                self.engine_color = self.pos.get_active_color();
+                self.think();
+            }
+            "playother" => {
+                self.force = false;
+                self.engine_color = self.pos.inactive_color();
+            }
+            "usermove" => match args.first() {
+                Some(str) => self.user_move(str),
+                None => println!("Error (missing move): {}", line),
+            },
+            "undo" => self.undo(1),
+            "remove" => self.undo(2),
+            "setboard" => match Position::from_fen(&args.join(" ")) {
+                Ok(pos) => {
+                    self.pos = pos;
+                    self.history.clear();
+                }
+                Err(_) => println!("tellusererror Illegal position"),
+            },
+            "level" => match parse_level(args) {
+                Some((moves_per_session, base, increment)) => {
+                    self.clocks.moves_per_session = moves_per_session;
+                    self.clocks.base = base;
+                    self.clocks.increment = increment;
+                    self.clocks.move_time = None;
+                }
+                None => println!("Error (invalid time control): {}", line),
+            },
+            "st" => match parse_arg::<u64>(args) {
+                Some(seconds) => self.clocks.move_time = Some(seconds * 1000),
+                None => println!("Error (invalid time): {}", line),
+            },
+            "sd" => match parse_arg::<usize>(args) {
+                Some(depth) => self.max_depth = Some(depth),
+                None => println!("Error (invalid depth): {}", line),
+            },
+            "time" | "otim" => match parse_arg::<u64>(args) {
+                Some(centiseconds) if command == "time" => self.clocks.engine = centiseconds * 10,
+                Some(centiseconds) => self.clocks.opponent = centiseconds * 10,
+                None => println!("Error (invalid time): {}", line),
+            },
+            "cores" => match parse_arg::<usize>(args) {
+                Some(cores) => self.threads = cores.max(1),
+                None => println!("Error (invalid core count): {}", line),
+            },
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "ping" => println!("pong {}", args.first().unwrap_or(&"")),
+            "egtpath" => match args {
+                ["syzygy", paths @ ..] => {
+                    self.tablebases = Some(Arc::new(Tablebases::new(&paths.join(" "))))
+                }
+                _ => println!("Error (unsupported tablebases): {}", line),
+            },
+            // without `usermove=1`, moves are sent bare
+            _ if self.pos.parse_uci_move(command).is_ok() => self.user_move(command),
+            _ => println!("Error (unknown command): {}", command),
+        };
+
+        true
+    }
+
+    fn user_move(&mut self, str: &str) {
+        let Ok(mv) = self.pos.parse_uci_move(str) else {
+            println!("Illegal move: {}", str);
+            return;
+        };
+
+        self.play_move(mv);
+
+        if !self.force && self.pos.get_active_color() == self.engine_color {
+            self.think();
+        }
+    }
+
+    fn play_move(&mut self, mv: Move) {
+        self.history.push((mv, self.pos.undo_info()));
+        self.pos.play_move(mv);
+    }
+
+    fn undo(&mut self, count: usize) {
+        for _ in 0..count {
+            if let Some((mv, undo_info)) = self.history.pop() {
+                self.pos.undo_move(mv, undo_info);
+            }
+        }
+    }
+
+    /// Starts searching for a move in the background.
+    /// The move is sent as soon as the search completes and is played on the board on the next command,
+    /// so that a reply of the GUI is read on the updated board.
+    fn think(&mut self) {
+        if self.pos.legal_moves().is_empty() {
+            return;
+        }
+
+        let mut ctx = self
+            .ctx
+            .take()
+            .unwrap_or_else(|| Context::new(engine::DEFAULT_HASH_SIZE_MB));
+        ctx.set_tablebases(self.tablebases.clone());
+        ctx.set_threads(self.threads);
+        let post = self.post;
+        let claimed = Arc::new(AtomicBool::new(false));
+        let thread_claimed = Arc::clone(&claimed);
+
+        let handle = SearchHandle::spawn(
+            self.pos.clone(),
+            ctx,
+            self.limits(),
+            move |_, iteration| {
+                if post {
+                    print_thinking(iteration);
+                }
+            },
+            move |mv| {
+                if mv != NULL_MOVE && !thread_claimed.swap(true, Ordering::SeqCst) {
+                    println!("move {}", to_uci(mv));
+                }
+            },
+        );
+
+        self.search = Some(RunningSearch { handle, claimed });
+    }
+
+    fn limits(&self) -> Limits {
+        let mut limits = Limits::depth(self.max_depth.unwrap_or(engine::MAX_DEPTH));
+
+        if self.clocks.move_time.is_some() {
+            limits.move_time = self.clocks.move_time;
+            return limits;
+        }
+
+        if self.clocks.base > 0 {
+            let mps = self.clocks.moves_per_session as u64;
+            let moves_played = (self.history.len() / 2) as u64;
+
+            limits.time = Some(self.clocks.engine);
+            limits.increment = self.clocks.increment;
+            limits.moves_to_go = (mps > 0).then(|| mps - moves_played % mps);
+        }
+
+        limits
+    }
+
+    /// Waits for the running search, or stops it if `move_now` is set, and plays its move.
+    fn finish_search(&mut self, move_now: bool) {
+        if let Some(search) = self.search.take() {
+            if move_now {
+                search.handle.stop();
+            }
+
+            let (mv, ctx) = search.handle.join();
+            self.ctx = Some(ctx);
+
+            if mv != NULL_MOVE {
+                self.play_move(mv);
+            }
+        }
+    }
+
+    /// Stops the running search without sending its move.
+    fn abort_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            // the move may have been sent right before the search was discarded
+            let sent = search.claimed.swap(true, Ordering::SeqCst);
+            let (mv, ctx) = search.handle.stop_and_join();
+            self.ctx = Some(ctx);
+
+            if sent {
+                self.play_move(mv);
+            }
+        }
+    }
+}
+
+/// Thinking output: `<ply> <score> <time> <nodes> <pv>`, with the score in centipawns
+/// or 100000 + N for a mate in N moves, and the time in centiseconds.
+fn print_thinking(iteration: &Iteration) {
+    let line = iteration.best_line();
+    let pv = line
+        .pv
+        .iter()
+        .map(|&mv| to_uci(mv))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    println!(
+        "{} {} {} {} {}",
+        iteration.depth,
+        xboard_score(line.score),
+        iteration.elapsed.as_millis() / 10,
+        iteration.nodes,
+        pv
+    );
+}
+
+fn xboard_score(score: Score) -> Score {
+    match engine::is_mate_score(score) {
+        true => {
+            let moves = engine::moves_to_mate(score);
+            ternary!(moves > 0, 100_000 + moves, -100_000 + moves)
+        }
+        false => engine::to_centipawns(score),
+    }
+}
+
+fn parse_arg<T: std::str::FromStr>(args: &[&str]) -> Option<T> {
+    args.first()?.parse::<T>().ok()
+}
+
+/// `level <moves per session> <base> <increment>` where the base is given in minutes
+/// (`5`) or minutes and seconds (`2:30`), and the increment in seconds.
+/// Returns the moves per session, the base and the increment in milliseconds.
+fn parse_level(args: &[&str]) -> Option<(u32, u64, u64)> {
+    let [moves_per_session, base, increment] = args else {
+        return None;
+    };
+
+    let moves_per_session = moves_per_session.parse::<u32>().ok()?;
+    let base = match base.split_once(':') {
+        Some((minutes, seconds)) => {
+            minutes.parse::<u64>().ok()? * 60_000 + seconds.parse::<u64>().ok()? * 1000
+        }
+        None => base.parse::<u64>().ok()? * 60_000,
+    };
+    let increment = (increment.parse::<f64>().ok()? * 1000.0) as u64;
+
+    Some((moves_per_session, base, increment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level() {
+        assert_eq!(parse_level(&["40", "5", "0"]), Some((40, 300_000, 0)));
+        assert_eq!(
+            parse_level(&["0", "2:30", "12"]),
+            Some((0, 150_000, 12_000))
+        );
+        assert_eq!(parse_level(&["0", "1", "0.5"]), Some((0, 60_000, 500)));
+        assert_eq!(parse_level(&["0", "1"]), None);
+    }
+
+    #[test]
+    fn mate_scores() {
+        assert_eq!(xboard_score(-1234), -123);
+        assert_eq!(xboard_score(engine::MATE_SCORE - 5), 100_003);
+        assert_eq!(xboard_score(-engine::MATE_SCORE + 4), -100_002);
+    }
+
+    #[test]
+    fn clocks_to_limits() {
+        let mut xboard = XBoard::new();
+
+        for command in ["level 40 5 0", "time 12000", "otim 15000", "force"] {
+            xboard.handle_command(command);
+        }
+
+        for mv in ["e2e4", "e7e5", "g1f3", "b8c6"] {
+            xboard.handle_command(mv);
+        }
+
+        let limits = xboard.limits();
+        assert_eq!(limits.time, Some(120_000));
+        assert_eq!(limits.moves_to_go, Some(38));
+
+        xboard.handle_command("st 2");
+        assert_eq!(xboard.limits().move_time, Some(2000));
+    }
+
+    #[test]
+    fn force_mode_and_undo() {
+        let mut xboard = XBoard::new();
+
+        for command in ["force", "usermove e2e4", "usermove e7e5", "usermove g1f3"] {
+            assert!(xboard.handle_command(command));
+        }
+
+        assert!(xboard.search.is_none());
+        assert_eq!(
+            xboard.pos.to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+
+        xboard.handle_command("remove");
+        assert_eq!(
+            xboard.pos.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+    }
+
+    #[test]
+    fn engine_replies_to_user_move() {
+        let mut xboard = XBoard::new();
+
+        for command in ["new", "cores 2", "sd 2", "usermove d2d4"] {
+            xboard.handle_command(command);
+        }
+
+        // waits for the engine's reply before answering
+        xboard.handle_command("sd 3");
+        assert_eq!(xboard.history.len(), 2);
+        assert_eq!(xboard.pos.get_active_color(), colors::WHITE);
+    }
+
+    #[test]
+    fn bare_moves() {
+        let mut xboard = XBoard::new();
+
+        for command in ["new", "sd 2", "e2e4"] {
+            xboard.handle_command(command);
+        }
+
+        while !xboard
+            .search
+            .as_ref()
+            .unwrap()
+            .claimed
+            .load(Ordering::SeqCst)
+        {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        // no black move can stop d4 after e4
+        xboard.handle_command("d2d4");
+        assert_eq!(xboard.history.len(), 3);
+        assert_eq!(to_uci(xboard.history[2].0), "d2d4");
+        assert!(xboard.search.is_some());
+        xboard.abort_search();
+    }
+
+    #[test]
+    fn informational_commands_while_thinking() {
+        let mut xboard = XBoard::new();
+
+        for command in ["new", "st 60", "usermove e2e4"] {
+            xboard.handle_command(command);
+        }
+
+        for command in [
+            "post",
+            "nopost",
+            "time 6000",
+            "otim 6000",
+            "easy",
+            "hard",
+            "computer",
+            "name Human",
+            "rating 2000 1800",
+            "accepted usermove",
+            "rejected ics",
+            "ping 2",
+        ] {
+            xboard.handle_command(command);
+            assert!(xboard.search.is_some(), "{} waited for the search", command);
+        }
+
+        xboard.handle_command("?");
+        assert!(xboard.search.is_none());
+        assert_eq!(xboard.history.len(), 2);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{
+    game::{
+        moves::encoding::{is_capture, is_promotion},
+        position::Position,
+    },
+    retrograde::{
+        Dtm, DtmTables,
+        material::{MAX_PIECES, Material},
+        unmoves::for_each_unmove,
+        values,
+    },
+};
+
+/// Marks the positions that cannot be lost, having a move to a draw or a win.
+const NEVER_LOST: u8 = u8::MAX;
+
+/// Computes the value of every index of a table by retrograde analysis. The tables that captures
+/// and promotions lead to must already be in `tables`.
+///
+/// Captures and promotions are resolved first from the other tables, as well as mates. Then, ply
+/// after ply, the positions lost in `n` plies make their predecessors won in `n + 1` plies, while
+/// the positions won in `n` plies count down the quiet moves of their predecessors, which are lost
+/// once every move loses. What remains is drawn.
+pub(super) fn generate(material: &Material, tables: &DtmTables) -> Vec<u8> {
+    let size = material.size();
+    let mut values = vec![values::DRAW; size];
+    // quiet moves not yet known to lose
+    let mut remaining = vec![0u8; size];
+    // the slowest loss through a capture or a promotion
+    let mut conversion_losses = vec![0u8; size];
+    let mut squares = [0; MAX_PIECES];
+    let mut placement = [(0, 0); MAX_PIECES];
+    let mut pos = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    let mut last_plies = 0;
+
+    for idx in 0..size {
+        let active_color = material.decode(idx, &mut squares);
+
+        for (i, &piece) in material.pieces.iter().enumerate() {
+            placement[i] = (piece, squares[i]);
+        }
+
+        if !material.is_valid(&squares)
+            || !pos.set_pieces(&placement[..material.pieces.len()], active_color)
+        {
+            values[idx] = values::INVALID;
+            continue;
+        }
+
+        let moves = pos.legal_moves();
+
+        if moves.is_empty() {
+            if pos.is_check() {
+                values[idx] = values::mate_in(0);
+            }
+
+            continue;
+        }
+
+        let undo_info = pos.undo_info();
+        let mut fastest_win = None;
+        let mut is_never_lost = false;
+
+        for &mv in moves.as_slice() {
+            if !is_capture(mv) && !is_promotion(mv) {
+                remaining[idx] += 1;
+                continue;
+            }
+
+            pos.play_move(mv);
+            let dtm = tables.probe(&pos).unwrap_or(Dtm::Draw);
+            pos.undo_move(mv, undo_info);
+
+            match dtm {
+                Dtm::Loss(plies) => {
+                    fastest_win = Some(fastest_win.unwrap_or(u8::MAX).min(plies + 1))
+                }
+                Dtm::Draw => is_never_lost = true,
+                Dtm::Win(plies) => {
+                    conversion_losses[idx] = conversion_losses[idx].max(plies + 1);
+                }
+            };
+        }
+
+        if let Some(plies) = fastest_win {
+            values[idx] = values::mate_in(plies);
+            remaining[idx] = NEVER_LOST;
+            last_plies = last_plies.max(plies);
+        } else if is_never_lost {
+            remaining[idx] = NEVER_LOST;
+        } else if remaining[idx] == 0 {
+            values[idx] = values::mate_in(conversion_losses[idx]);
+            last_plies = last_plies.max(conversion_losses[idx]);
+        }
+    }
+
+    let mut plies = 0;
+
+    // wins found through captures or promotions may still be shortened
+    while plies <= last_plies {
+        let value = values::mate_in(plies);
+
+        for idx in 0..size {
+            if values[idx] != value {
+                continue;
+            }
+
+            let active_color = material.decode(idx, &mut squares);
+
+            for_each_unmove(material, &squares, active_color, |prev_idx| {
+                let prev_value = values[prev_idx];
+
+                if plies % 2 == 0 {
+                    let is_slower_win = values::plies(prev_value)
+                        .is_some_and(|prev_plies| prev_plies % 2 == 1 && prev_plies > plies + 1);
+
+                    if prev_value == values::DRAW || is_slower_win {
+                        values[prev_idx] = values::mate_in(plies + 1);
+                        last_plies = last_plies.max(plies + 1);
+                    }
+                } else if prev_value == values::DRAW && remaining[prev_idx] != NEVER_LOST {
+                    remaining[prev_idx] -= 1;
+
+                    if remaining[prev_idx] == 0 {
+                        let loss = (plies + 1).max(conversion_losses[prev_idx]);
+                        values[prev_idx] = values::mate_in(loss);
+                        last_plies = last_plies.max(loss);
+                    }
+                }
+            });
+        }
+
+        plies += 1;
+    }
+
+    values
+}
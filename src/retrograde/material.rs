@@ -0,0 +1,254 @@
+use std::cmp::Reverse;
+
+use crate::{
+    game::{
+        board::{NB_SQUARES, colors, pieces},
+        position::Position,
+    },
+    macros::ternary,
+};
+
+/// Tables are small enough to hold every placement of their pieces.
+pub(super) const MAX_PIECES: usize = 4;
+
+/// The initials of the pieces in the order of table names.
+const INITIALS: &str = "KQRBNP";
+
+/// The pieces of a table in index order: the two kings, then the other white and black pieces
+/// from the queens to the pawns.
+pub(super) struct Material {
+    pub(super) name: String,
+    pub(super) pieces: Vec<usize>,
+}
+
+impl Material {
+    /// Names like `KRKP`: White's pieces, then Black's, each side starting with its king.
+    /// The pieces of a side may come in any order. Returns `None` for invalid names.
+    pub(super) fn parse(name: &str) -> Option<Self> {
+        let black_start = name.get(1..)?.find('K')? + 1;
+        let (white, black) = name.split_at(black_start);
+        let is_valid_side =
+            |side: &str| side.starts_with('K') && side[1..].chars().all(|ch| "QRBNP".contains(ch));
+
+        if !is_valid_side(white) || !is_valid_side(black) || name.len() > MAX_PIECES {
+            return None;
+        }
+
+        let (white, black) = (sort_side(white), sort_side(black));
+        let mut pieces = vec![pieces::WHITE_KING, pieces::BLACK_KING];
+
+        for (side, color) in [(&white, colors::WHITE), (&black, colors::BLACK)] {
+            pieces.extend(side.chars().skip(1).map(|initial| {
+                let piece = pieces::from_initial(initial);
+                ternary!(color == colors::WHITE, piece, pieces::rev_color(piece))
+            }));
+        }
+
+        Some(Self {
+            name: white + &black,
+            pieces,
+        })
+    }
+
+    /// The number of indices, two per placement of the pieces for the side to move.
+    pub(super) fn size(&self) -> usize {
+        2 << (6 * self.pieces.len())
+    }
+
+    /// Fills the squares of the pieces and returns the side to move.
+    pub(super) fn decode(&self, idx: usize, squares: &mut [usize]) -> usize {
+        let mut rest = idx >> 1;
+
+        for sq in squares.iter_mut().take(self.pieces.len()) {
+            *sq = rest % NB_SQUARES;
+            rest /= NB_SQUARES;
+        }
+
+        idx & 1
+    }
+
+    pub(super) fn encode(&self, squares: &[usize], active_color: usize) -> usize {
+        let placement = squares[..self.pieces.len()]
+            .iter()
+            .rev()
+            .fold(0, |idx, &sq| idx * NB_SQUARES + sq);
+
+        placement << 1 | active_color
+    }
+
+    /// Pieces stand on different squares and pawns aren't on the back ranks.
+    pub(super) fn is_valid(&self, squares: &[usize]) -> bool {
+        let mut occupancy = 0u64;
+
+        for (&piece, &sq) in self.pieces.iter().zip(squares) {
+            if occupancy & 1 << sq != 0 || (pieces::is_pawn(piece) && !(8..56).contains(&sq)) {
+                return false;
+            }
+
+            occupancy |= 1 << sq;
+        }
+
+        true
+    }
+
+    /// The index of a position with this material, whose colors are swapped and board mirrored
+    /// if `is_flipped` is set.
+    pub(super) fn index_of(&self, pos: &Position, is_flipped: bool) -> usize {
+        let mut squares = [0; MAX_PIECES];
+        let mut used = 0u64;
+
+        for (i, &piece) in self.pieces.iter().enumerate() {
+            let actual_piece = ternary!(is_flipped, pieces::rev_color(piece), piece);
+            let sq = (pos.piece_occupancy(actual_piece) & !used).trailing_zeros() as usize;
+            used |= 1 << sq;
+            squares[i] = ternary!(is_flipped, sq ^ 56, sq);
+        }
+
+        self.encode(&squares, pos.get_active_color() ^ is_flipped as usize)
+    }
+
+    /// The canonical names of the tables a capture or a promotion leads to, except the bare kings.
+    pub(super) fn conversions(&self) -> Vec<String> {
+        let black_start = self.name[1..].find('K').unwrap() + 1;
+        let (white, black) = self.name.split_at(black_start);
+        let mut names = Vec::new();
+
+        for (side, other) in [(white, black), (black, white)] {
+            let mut sides = vec![side.to_string()];
+
+            for (i, _) in side.match_indices('P') {
+                for promoted in ['Q', 'R', 'B', 'N'] {
+                    let mut promoted_side = side.to_string();
+                    promoted_side.replace_range(i..i + 1, &promoted.to_string());
+                    sides.push(sort_side(&promoted_side));
+                    names.push(canonical_name(&sort_side(&promoted_side), other).0);
+                }
+            }
+
+            // captures, possibly with a promotion
+            for new_side in &sides {
+                for (i, _) in other.char_indices().skip(1) {
+                    let mut captured_side = other.to_string();
+                    captured_side.remove(i);
+                    names.push(canonical_name(new_side, &captured_side).0);
+                }
+            }
+        }
+
+        names.retain(|name| name != "KK");
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// The name of a side in a position, e.g. `KRP`.
+pub(super) fn side_name(pos: &Position, color: usize) -> String {
+    INITIALS
+        .chars()
+        .map(|initial| {
+            let piece = pieces::from_initial(initial);
+            let piece = ternary!(color == colors::WHITE, piece, pieces::rev_color(piece));
+            initial
+                .to_string()
+                .repeat(pos.piece_occupancy(piece).count_ones() as usize)
+        })
+        .collect()
+}
+
+/// Tables are stored with the stronger side as White, the other orientation being probed
+/// with colors flipped. Returns the name of the table and whether colors are flipped.
+pub(super) fn canonical_name(white: &str, black: &str) -> (String, bool) {
+    let strength = |side: &str| {
+        let value = side
+            .chars()
+            .map(|initial| match initial {
+                'Q' => 9,
+                'R' => 5,
+                'B' | 'N' => 3,
+                'P' => 1,
+                _ => 0,
+            })
+            .sum::<u32>();
+        let initials = side
+            .chars()
+            .map(|initial| Reverse(INITIALS.find(initial)))
+            .collect::<Vec<_>>();
+        (value, initials)
+    };
+
+    match strength(black) > strength(white) {
+        true => (format!("{}{}", black, white), true),
+        false => (format!("{}{}", white, black), false),
+    }
+}
+
+fn sort_side(side: &str) -> String {
+    let mut initials = side.chars().collect::<Vec<_>>();
+    initials.sort_by_key(|&initial| INITIALS.find(initial));
+    initials.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        let material = Material::parse("KPKR").unwrap();
+
+        assert_eq!(material.name, "KPKR");
+        assert_eq!(
+            material.pieces,
+            [
+                pieces::WHITE_KING,
+                pieces::BLACK_KING,
+                pieces::WHITE_PAWN,
+                pieces::BLACK_ROOK
+            ]
+        );
+        assert_eq!(Material::parse("KNBK").unwrap().name, "KBNK");
+        assert!(Material::parse("KRR").is_none());
+        assert!(Material::parse("KRKXP").is_none());
+        assert!(Material::parse("KQRKR").is_none());
+        assert_eq!(canonical_name("KP", "KR"), ("KRKP".to_string(), true));
+        assert_eq!(canonical_name("KB", "KN"), ("KBKN".to_string(), false));
+        assert_eq!(canonical_name("KN", "KB"), ("KBKN".to_string(), true));
+    }
+
+    #[test]
+    fn conversions() {
+        let material = Material::parse("KRKP").unwrap();
+
+        assert_eq!(
+            material.conversions(),
+            [
+                "KBK", "KNK", "KPK", "KQK", "KQKR", "KRK", "KRKB", "KRKN", "KRKR"
+            ]
+        );
+        assert_eq!(
+            Material::parse("KPK").unwrap().conversions(),
+            ["KBK", "KNK", "KQK", "KRK"]
+        );
+    }
+
+    #[test]
+    fn indices() {
+        let material = Material::parse("KRKP").unwrap();
+        let mut squares = [0; MAX_PIECES];
+        let idx = material.encode(&[4, 60, 0, 52], colors::BLACK);
+
+        assert_eq!(material.decode(idx, &mut squares), colors::BLACK);
+        assert_eq!(squares, [4, 60, 0, 52]);
+        assert!(material.is_valid(&squares));
+        assert!(!material.is_valid(&[4, 60, 0, 60]));
+        assert!(!material.is_valid(&[4, 60, 0, 3]));
+
+        // the same position with colors flipped
+        let pos = Position::from_fen("4k3/4p3/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        let flipped = Position::from_fen("r3k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+
+        assert_eq!(material.index_of(&pos, false), idx);
+        assert_eq!(material.index_of(&flipped, true), idx);
+    }
+}
@@ -0,0 +1,341 @@
+//! Distance-to-mate tables of endings with up to 4 pieces, generated by retrograde analysis.
+//! The distance is counted in plies, ignoring the fifty-move rule and en passant captures.
+
+mod generator;
+mod material;
+mod unmoves;
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use crate::game::{board::colors, position::Position};
+
+use material::{Material, canonical_name, side_name};
+
+/// The magic number at the start of table files.
+const MAGIC: &[u8; 4] = b"RDTM";
+
+const FILE_EXTENSION: &str = "dtm";
+
+/// Tables store a byte per index.
+mod values {
+    pub(super) const DRAW: u8 = 0;
+    /// Overlapping pieces, pawns on a back rank or the side not to move in check.
+    pub(super) const INVALID: u8 = u8::MAX;
+
+    /// Mates are stored as the number of plies plus one. The side to move wins when the number of
+    /// plies is odd and loses when it is even.
+    pub(super) const fn mate_in(plies: u8) -> u8 {
+        plies + 1
+    }
+
+    pub(super) const fn plies(value: u8) -> Option<u8> {
+        match value {
+            DRAW | INVALID => None,
+            _ => Some(value - 1),
+        }
+    }
+}
+
+/// The result of a position from the point of view of the side to move, with the number of
+/// plies until mate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Dtm {
+    Win(u8),
+    Draw,
+    Loss(u8),
+}
+
+impl Dtm {
+    const fn from_value(value: u8) -> Option<Self> {
+        match values::plies(value) {
+            Some(plies) if plies % 2 == 1 => Some(Self::Win(plies)),
+            Some(plies) => Some(Self::Loss(plies)),
+            None if value == values::DRAW => Some(Self::Draw),
+            None => None,
+        }
+    }
+}
+
+/// The number of positions of each result in a table, each placement of the pieces counting
+/// once for each side to move.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Summary {
+    pub(crate) wins: usize,
+    pub(crate) draws: usize,
+    pub(crate) losses: usize,
+    /// The most plies needed to mate.
+    pub(crate) longest_mate: u8,
+}
+
+struct Table {
+    material: Material,
+    values: Vec<u8>,
+}
+
+/// Tables keyed by name, like `KRKP`, with the stronger side first.
+#[derive(Default)]
+pub(crate) struct DtmTables {
+    tables: HashMap<String, Table>,
+}
+
+impl DtmTables {
+    /// Reads the tables of a directory. Files that aren't tables are ignored.
+    pub(crate) fn open(dir: &Path) -> io::Result<Self> {
+        let mut tables = Self::default();
+
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path.extension().is_some_and(|ext| ext == FILE_EXTENSION)
+                && let Some(table) = Table::from_bytes(&fs::read(&path)?)
+            {
+                tables.tables.insert(table.material.name.clone(), table);
+            }
+        }
+
+        Ok(tables)
+    }
+
+    /// Writes every table to a file named after it.
+    pub(crate) fn save(&self, dir: &Path) -> io::Result<()> {
+        for (name, table) in &self.tables {
+            let path = dir.join(format!("{}.{}", name, FILE_EXTENSION));
+            fs::write(path, table.to_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// The names of the tables, sorted.
+    pub(crate) fn names(&self) -> Vec<&str> {
+        let mut names = self.tables.keys().map(String::as_str).collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Generates a table along with the tables captures and promotions lead to, unless they are
+    /// already there. Returns the canonical name of the table, or `None` if the name is invalid.
+    pub(crate) fn generate(&mut self, name: &str) -> Option<String> {
+        let material = Material::parse(name)?;
+        let (white, black) = material.name.split_at(material.name[1..].find('K')? + 1);
+        let (name, _) = canonical_name(white, black);
+
+        if !self.tables.contains_key(&name) {
+            let material = Material::parse(&name)?;
+
+            for conversion in material.conversions() {
+                self.generate(&conversion);
+            }
+
+            let values = generator::generate(&material, self);
+            self.tables.insert(name.clone(), Table { material, values });
+        }
+
+        Some(name)
+    }
+
+    /// Returns `None` when the position has castling rights or its table is missing.
+    pub(crate) fn probe(&self, pos: &Position) -> Option<Dtm> {
+        if pos.get_castling_rights() != 0 {
+            return None;
+        }
+
+        if pos.piece_count() == 2 {
+            return Some(Dtm::Draw);
+        }
+
+        let white = side_name(pos, colors::WHITE);
+        let black = side_name(pos, colors::BLACK);
+        let (name, is_flipped) = canonical_name(&white, &black);
+        let table = self.tables.get(&name)?;
+
+        Dtm::from_value(table.values[table.material.index_of(pos, is_flipped)])
+    }
+
+    pub(crate) fn summary(&self, name: &str) -> Option<Summary> {
+        let mut summary = Summary::default();
+
+        for &value in &self.tables.get(name)?.values {
+            match Dtm::from_value(value) {
+                Some(Dtm::Win(plies)) => {
+                    summary.wins += 1;
+                    summary.longest_mate = summary.longest_mate.max(plies);
+                }
+                Some(Dtm::Draw) => summary.draws += 1,
+                Some(Dtm::Loss(_)) => summary.losses += 1,
+                None => {}
+            };
+        }
+
+        Some(summary)
+    }
+}
+
+impl Table {
+    /// The magic number and the name of the table, then the values with White to move followed
+    /// by those with Black to move, which are packed into runs: a header byte below 128 is
+    /// followed by that many values plus one, while a higher one repeats the next value
+    /// `header - 126` times.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(self.material.name.len() as u8);
+        bytes.extend(self.material.name.as_bytes());
+
+        let values = (0..2)
+            .flat_map(|color| self.values.iter().skip(color).step_by(2).copied())
+            .collect::<Vec<_>>();
+        let mut literals = Vec::new();
+
+        for run in values.chunk_by(|a, b| a == b) {
+            for chunk in run.chunks(MAX_RUN) {
+                if chunk.len() > 2 {
+                    pack_literals(&mut bytes, &mut literals);
+                    bytes.extend([(chunk.len() + 126) as u8, chunk[0]]);
+                } else {
+                    literals.extend(chunk);
+                }
+            }
+        }
+
+        pack_literals(&mut bytes, &mut literals);
+        bytes
+    }
+
+    /// Returns `None` if the bytes don't hold a whole table.
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let name_len = *bytes.get(MAGIC.len())? as usize;
+        let runs_start = MAGIC.len() + 1 + name_len;
+
+        if bytes.get(..MAGIC.len())? != MAGIC {
+            return None;
+        }
+
+        let name = std::str::from_utf8(bytes.get(MAGIC.len() + 1..runs_start)?).ok()?;
+        let material = Material::parse(name)?;
+        let size = material.size();
+        let mut unpacked = Vec::with_capacity(size);
+        let mut i = runs_start;
+
+        while let Some(&header) = bytes.get(i) {
+            let header = header as usize;
+
+            if header < 128 {
+                unpacked.extend(bytes.get(i + 1..i + header + 2)?);
+                i += header + 2;
+            } else {
+                unpacked.extend(std::iter::repeat_n(*bytes.get(i + 1)?, header - 126));
+                i += 2;
+            }
+        }
+
+        if unpacked.len() != size {
+            return None;
+        }
+
+        let (white, black) = unpacked.split_at(size / 2);
+        let values = white
+            .iter()
+            .zip(black)
+            .flat_map(|(&white, &black)| [white, black])
+            .collect();
+
+        Some(Self { material, values })
+    }
+}
+
+/// The longest run of equal values a header byte can hold.
+const MAX_RUN: usize = u8::MAX as usize - 126;
+
+fn pack_literals(bytes: &mut Vec<u8>, literals: &mut Vec<u8>) {
+    for chunk in literals.chunks(128) {
+        bytes.push(chunk.len() as u8 - 1);
+        bytes.extend(chunk);
+    }
+
+    literals.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(tables: &DtmTables, fen: &str) -> Option<Dtm> {
+        tables.probe(&Position::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    #[ignore = "slow in debug builds, run with `cargo test --release -- --ignored`"]
+    fn queen_and_rook_mates() {
+        let mut tables = DtmTables::default();
+        assert_eq!(tables.generate("KKQ").as_deref(), Some("KQK"));
+        tables.generate("KRK").unwrap();
+
+        let kqk = tables.summary("KQK").unwrap();
+        let krk = tables.summary("KRK").unwrap();
+
+        // the longest mates take 10 and 16 moves
+        assert_eq!(kqk.longest_mate, 19);
+        assert_eq!(krk.longest_mate, 31);
+        assert!(krk.draws > 0 && krk.losses > 0);
+
+        assert_eq!(
+            probe(&tables, "7k/8/6K1/8/8/8/8/R7 w - - 0 1"),
+            Some(Dtm::Win(1))
+        );
+        assert_eq!(
+            probe(&tables, "R6k/8/6K1/8/8/8/8/8 b - - 0 1"),
+            Some(Dtm::Loss(0))
+        );
+        // the king takes the rook
+        assert_eq!(
+            probe(&tables, "7k/8/8/8/8/8/6r1/7K w - - 0 1"),
+            Some(Dtm::Draw)
+        );
+        // colors flipped
+        assert_eq!(
+            probe(&tables, "r7/8/8/8/8/6k1/8/7K b - - 0 1"),
+            Some(Dtm::Win(1))
+        );
+        assert_eq!(probe(&tables, "8/8/8/8/8/8/8/KB5k w - - 0 1"), None);
+        assert_eq!(
+            probe(&tables, "8/8/8/8/8/8/8/K6k w - - 0 1"),
+            Some(Dtm::Draw)
+        );
+    }
+
+    #[test]
+    fn files() {
+        let material = Material::parse("KRK").unwrap();
+        let size = material.size();
+        let mut values = vec![values::DRAW; size];
+        values[..100].fill(values::INVALID);
+        values[1000..1300].fill(values::mate_in(3));
+        values[2000] = values::mate_in(8);
+
+        let mut tables = DtmTables::default();
+        tables
+            .tables
+            .insert(material.name.clone(), Table { material, values });
+        assert_eq!(tables.generate("KQRKR"), None);
+
+        let dir = std::env::temp_dir().join(format!("dtm-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        tables.save(&dir).unwrap();
+        fs::write(dir.join("KBK.dtm"), b"RDTM\x03KBK\xff\x00").unwrap();
+
+        let loaded = DtmTables::open(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.names(), ["KRK"]);
+        assert_eq!(loaded.tables["KRK"].values, tables.tables["KRK"].values);
+        assert_eq!(
+            loaded.summary("KRK"),
+            Some(Summary {
+                wins: 300,
+                draws: size - 401,
+                losses: 1,
+                longest_mate: 3,
+            })
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{
+    bit_boards::is_bit_set,
+    game::{
+        board::{colors, pieces},
+        moves::piece_attacks,
+    },
+    macros::ternary,
+    retrograde::material::{MAX_PIECES, Material},
+};
+
+/// Calls `f` with the index of every position the side not to move could have played a quiet
+/// move from, i.e. a move that is neither a capture nor a promotion, to reach the position.
+/// The positions may be illegal, the side to move being in check.
+pub(super) fn for_each_unmove(
+    material: &Material,
+    squares: &[usize],
+    active_color: usize,
+    mut f: impl FnMut(usize),
+) {
+    let mover = colors::rev(active_color);
+    let occupancy = squares[..material.pieces.len()]
+        .iter()
+        .fold(0u64, |occupancy, &sq| occupancy | 1 << sq);
+    let mut prev_squares = [0; MAX_PIECES];
+    prev_squares[..squares.len()].copy_from_slice(squares);
+
+    for (i, &piece) in material.pieces.iter().enumerate() {
+        if pieces::color_of(piece) != mover {
+            continue;
+        }
+
+        let mut sources = match pieces::is_pawn(piece) {
+            true => pawn_sources(squares[i], mover, occupancy),
+            false => piece_attacks(piece, squares[i], occupancy) & !occupancy,
+        };
+
+        while sources != 0 {
+            prev_squares[i] = sources.trailing_zeros() as usize;
+            sources &= sources - 1;
+            f(material.encode(&prev_squares, mover));
+        }
+
+        prev_squares[i] = squares[i];
+    }
+}
+
+/// The squares a pawn may have been pushed from, one or two ranks behind.
+fn pawn_sources(sq: usize, color: usize, occupancy: u64) -> u64 {
+    let is_white = color == colors::WHITE;
+    let (rank, behind) = (sq / 8, ternary!(is_white, sq.wrapping_sub(8), sq + 8));
+    let (second_rank, fourth_rank) = ternary!(is_white, (1, 3), (6, 4));
+
+    if !(1..7).contains(&(behind / 8)) || is_bit_set(occupancy, behind) {
+        return 0;
+    }
+
+    let mut sources = 1 << behind;
+
+    if rank == fourth_rank {
+        let double_behind = ternary!(is_white, sq - 16, sq + 16);
+
+        if double_behind / 8 == second_rank && !is_bit_set(occupancy, double_behind) {
+            sources |= 1 << double_behind;
+        }
+    }
+
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{board::squares, position::Position};
+
+    use super::*;
+
+    /// Checks that a quiet move leads back to the position from every legal predecessor.
+    fn assert_unmoves(name: &str, fen: &str) {
+        let material = Material::parse(name).unwrap();
+        let mut pos = Position::from_fen(fen).unwrap();
+        let idx = material.index_of(&pos, false);
+        let mut squares = [0; MAX_PIECES];
+        let active_color = material.decode(idx, &mut squares);
+        let mut unmoves = Vec::new();
+
+        for_each_unmove(&material, &squares, active_color, |prev_idx| {
+            unmoves.push(prev_idx)
+        });
+
+        assert!(!unmoves.is_empty());
+
+        for prev_idx in unmoves {
+            let color = material.decode(prev_idx, &mut squares);
+            let placement = material
+                .pieces
+                .iter()
+                .zip(squares)
+                .map(|(&piece, sq)| (piece, sq))
+                .collect::<Vec<_>>();
+
+            if !pos.set_pieces(&placement, color) {
+                continue;
+            }
+
+            let undo_info = pos.undo_info();
+            let leads_back = pos.legal_moves().as_slice().iter().any(|&mv| {
+                pos.play_move(mv);
+                let is_same = material.index_of(&pos, false) == idx;
+                pos.undo_move(mv, undo_info);
+                is_same
+            });
+
+            assert!(leads_back, "{}", pos.to_fen());
+        }
+    }
+
+    #[test]
+    fn piece_unmoves() {
+        let material = Material::parse("KRK").unwrap();
+        let squares = [squares::A1, squares::H8, squares::C3];
+        let mut count = 0;
+
+        // black just moved: only its king has unmoves
+        for_each_unmove(&material, &squares, colors::WHITE, |_| count += 1);
+        assert_eq!(count, 3);
+
+        assert_unmoves("KRK", "7k/8/8/8/3R4/8/8/K7 b - - 0 1");
+        assert_unmoves("KQKR", "7k/8/2r5/8/3Q4/8/8/K7 b - - 0 1");
+    }
+
+    #[test]
+    fn pawn_unmoves() {
+        let material = Material::parse("KPK").unwrap();
+        let mut count = 0;
+
+        for_each_unmove(
+            &material,
+            &[squares::H1, squares::H8, squares::E4],
+            colors::BLACK,
+            |_| count += 1,
+        );
+        // e3, e2 and the three king squares
+        assert_eq!(count, 5);
+
+        count = 0;
+        for_each_unmove(
+            &material,
+            &[squares::H1, squares::H8, squares::E2],
+            colors::BLACK,
+            |_| count += 1,
+        );
+        assert_eq!(count, 3);
+
+        assert_unmoves("KPK", "8/8/8/8/4P3/8/8/K6k b - - 0 1");
+        assert_unmoves("KRKP", "8/8/8/4p3/8/8/R7/K6k w - - 0 1");
+    }
+}
@@ -0,0 +1,223 @@
+//! The tables used to turn the squares of the pieces into an index of a Syzygy table.
+//! Positions are mirrored so that the leading piece stands in the a1-d1-d4 triangle, or the
+//! leading pawn on files a to d, before being encoded.
+
+use crate::{
+    bit_boards::is_bit_set,
+    game::{
+        board::{NB_SQUARES, pieces, squares},
+        moves::piece_attacks,
+    },
+    macros::{const_while, ternary},
+};
+
+/// Up to 5 pawns of the same color may lead in a 7-piece table.
+pub(super) const MAX_LEAD_PAWNS: usize = 5;
+
+/// The number of ways to place two kings when the first one is in the a1-d1-d4 triangle.
+pub(super) const NB_KING_PAIRS: u64 = 462;
+
+/// The number of ways to place three unique pieces when the first one is in the triangle.
+pub(super) const NB_UNIQUE_TRIPLES: u64 = 31332;
+
+/// Positive above the a1-h8 diagonal, zero on it.
+pub(super) const fn off_diagonal(sq: usize) -> i32 {
+    (sq / 8) as i32 - (sq % 8) as i32
+}
+
+pub(super) const fn flip_file(sq: usize) -> usize {
+    sq ^ 7
+}
+
+pub(super) const fn flip_rank(sq: usize) -> usize {
+    sq ^ 56
+}
+
+/// Mirrors a square along the a1-h8 diagonal.
+pub(super) const fn flip_diagonal(sq: usize) -> usize {
+    ((sq >> 3) | (sq << 3)) & 63
+}
+
+/// The distance of a file to the nearest edge.
+pub(super) const fn edge_distance(file: usize) -> usize {
+    ternary!(file < 4, file, 7 - file)
+}
+
+/// Maps the 28 squares below the a1-h8 diagonal to 0..27.
+pub(super) const MAP_B1H1H7: [u64; NB_SQUARES] = {
+    let mut map = [0; NB_SQUARES];
+    let mut code = 0;
+
+    const_while!(sq, 0, NB_SQUARES, {
+        if off_diagonal(sq) < 0 {
+            map[sq] = code;
+            code += 1;
+        }
+    });
+
+    map
+};
+
+/// Maps the a1-d1-d4 triangle to 0..9, the squares of the diagonal coming last.
+pub(super) const MAP_A1D1D4: [u64; NB_SQUARES] = {
+    const TRIANGLE: [usize; 10] = [
+        squares::B1,
+        squares::C1,
+        squares::D1,
+        squares::C2,
+        squares::D2,
+        squares::D3,
+        squares::A1,
+        squares::B2,
+        squares::C3,
+        squares::D4,
+    ];
+    let mut map = [0; NB_SQUARES];
+
+    const_while!(i, 0, TRIANGLE.len(), {
+        map[TRIANGLE[i]] = i as u64;
+    });
+
+    map
+};
+
+/// Encodes the 462 legal placements of two kings, the first one being in the a1-d1-d4 triangle.
+/// When the first king is on the diagonal, the second one isn't above it, and placements with
+/// both kings on the diagonal come last.
+pub(super) const MAP_KK: [[u64; NB_SQUARES]; 10] = {
+    let mut map = [[0; NB_SQUARES]; 10];
+    let mut both_on_diagonal = [(0, 0); 10 * NB_SQUARES];
+    let mut nb_both_on_diagonal = 0;
+    let mut code = 0;
+
+    const_while!(idx, 0, 10, {
+        const_while!(sq1, 0, squares::D4 + 1, {
+            // the squares outside of the triangle are mapped to 0 as well as b1
+            if MAP_A1D1D4[sq1] == idx as u64 && (idx != 0 || sq1 == squares::B1) {
+                let king_zone = piece_attacks(pieces::WHITE_KING, sq1, 0) | 1 << sq1;
+
+                const_while!(sq2, 0, NB_SQUARES, {
+                    if is_bit_set(king_zone, sq2) {
+                        // adjacent kings
+                    } else if off_diagonal(sq1) == 0 && off_diagonal(sq2) > 0 {
+                        // mirrored by another placement
+                    } else if off_diagonal(sq1) == 0 && off_diagonal(sq2) == 0 {
+                        both_on_diagonal[nb_both_on_diagonal] = (idx, sq2);
+                        nb_both_on_diagonal += 1;
+                    } else {
+                        map[idx][sq2] = code;
+                        code += 1;
+                    }
+                });
+            }
+        });
+    });
+
+    const_while!(i, 0, nb_both_on_diagonal, {
+        let (idx, sq2) = both_on_diagonal[i];
+        map[idx][sq2] = code;
+        code += 1;
+    });
+
+    map
+};
+
+/// `BINOMIAL[k][n]` is the number of ways to choose `k` squares out of `n`.
+pub(super) const BINOMIAL: [[u64; NB_SQUARES]; 7] = {
+    let mut binomial = [[0; NB_SQUARES]; 7];
+    binomial[0][0] = 1;
+
+    const_while!(n, 1, NB_SQUARES, {
+        const_while!(k, 0, 7, {
+            if k <= n {
+                let left = ternary!(k > 0, binomial[k - 1][n - 1], 0);
+                let right = ternary!(k < n, binomial[k][n - 1], 0);
+                binomial[k][n] = left + right;
+            }
+        });
+    });
+
+    binomial
+};
+
+/// Maps the squares a2-h7 to 0..47. The leading pawn is the one with the highest value:
+/// the nearest to an edge and, on the same file, the lowest one.
+pub(super) const MAP_PAWNS: [u64; NB_SQUARES] = {
+    let mut map = [0; NB_SQUARES];
+    let mut nb_mapped = 0;
+
+    const_while!(file, 0, 4, {
+        const_while!(rank, 1, 7, {
+            let sq = rank * 8 + file;
+            map[sq] = 47 - nb_mapped;
+            map[flip_file(sq)] = 46 - nb_mapped;
+            nb_mapped += 2;
+        });
+    });
+
+    map
+};
+
+/// The index of a group of `n` leading pawns whose first pawn is on a given square, counted from
+/// the first pawn being on the second rank of its file.
+pub(super) const LEAD_PAWN_IDX: [[u64; NB_SQUARES]; MAX_LEAD_PAWNS + 1] = lead_pawn_tables().0;
+
+/// The number of placements of `n` leading pawns when the first pawn is on a given file.
+pub(super) const LEAD_PAWNS_SIZE: [[u64; 4]; MAX_LEAD_PAWNS + 1] = lead_pawn_tables().1;
+
+#[allow(clippy::type_complexity)]
+const fn lead_pawn_tables() -> (
+    [[u64; NB_SQUARES]; MAX_LEAD_PAWNS + 1],
+    [[u64; 4]; MAX_LEAD_PAWNS + 1],
+) {
+    let mut lead_pawn_idx = [[0; NB_SQUARES]; MAX_LEAD_PAWNS + 1];
+    let mut lead_pawns_size = [[0; 4]; MAX_LEAD_PAWNS + 1];
+
+    const_while!(nb_pawns, 1, MAX_LEAD_PAWNS + 1, {
+        const_while!(file, 0, 4, {
+            let mut idx = 0;
+
+            const_while!(rank, 1, 7, {
+                let sq = rank * 8 + file;
+                lead_pawn_idx[nb_pawns][sq] = idx;
+                idx += BINOMIAL[nb_pawns - 1][MAP_PAWNS[sq] as usize];
+            });
+
+            lead_pawns_size[nb_pawns][file] = idx;
+        });
+    });
+
+    (lead_pawn_idx, lead_pawns_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn king_pairs() {
+        let max_code = MAP_KK
+            .iter()
+            .flat_map(|row| row.iter())
+            .copied()
+            .max()
+            .unwrap();
+
+        assert_eq!(max_code, NB_KING_PAIRS - 1);
+        assert_eq!(MAP_A1D1D4[squares::D4], 9);
+        assert_eq!(MAP_B1H1H7[squares::H7], 27);
+    }
+
+    #[test]
+    fn pawns() {
+        assert_eq!(MAP_PAWNS[squares::A2], 47);
+        assert_eq!(MAP_PAWNS[squares::H2], 46);
+        assert_eq!(MAP_PAWNS[squares::A3], 45);
+        assert_eq!(MAP_PAWNS[squares::E7], 0);
+        assert_eq!(BINOMIAL[2][5], 10);
+        assert_eq!(BINOMIAL[6][63], 67_945_521);
+        // a single leading pawn may stand on any of the 6 squares of its file
+        assert_eq!(LEAD_PAWNS_SIZE[1], [6; 4]);
+        assert_eq!(LEAD_PAWN_IDX[1][squares::A4], 2);
+    }
+}
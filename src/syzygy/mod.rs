@@ -0,0 +1,531 @@
+//! Probing of Syzygy endgame tablebases. WDL tables give the result of a position under the
+//! fifty-move rule and DTZ tables the number of plies until the next capture or pawn move.
+//! Tables are read from `.rtbw` and `.rtbz` files the first time they are needed.
+
+mod encoding;
+mod table;
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{
+    game::{
+        board::{
+            colors,
+            pieces::{self, piece_types},
+        },
+        moves::{
+            Move,
+            encoding::{is_capture, src_piece},
+        },
+        position::Position,
+    },
+    macros::ternary,
+};
+
+use table::{Kind, Probe, Table};
+
+pub(crate) type Wdl = i32;
+
+/// Results from the point of view of the side to move. A cursed win would be a win and a blessed
+/// loss a loss without the fifty-move rule.
+pub(crate) mod wdl {
+    use super::Wdl;
+
+    pub(crate) const LOSS: Wdl = -2;
+    pub(crate) const BLESSED_LOSS: Wdl = -1;
+    pub(crate) const DRAW: Wdl = 0;
+    pub(crate) const CURSED_WIN: Wdl = 1;
+    pub(crate) const WIN: Wdl = 2;
+}
+
+/// The rank of a root move that wins before the fifty-move rule could apply.
+pub(crate) const MAX_DTZ: i32 = 1 << 18;
+
+const MAX_PIECES: usize = 7;
+const PATH_SEPARATOR: char = ternary!(cfg!(windows), ';', ':');
+
+/// The initials of the pieces in the order of table names.
+const PIECE_INITIALS: [(usize, char); 6] = [
+    (piece_types::KING, 'K'),
+    (piece_types::QUEEN, 'Q'),
+    (piece_types::ROOK, 'R'),
+    (piece_types::BISHOP, 'B'),
+    (piece_types::KNIGHT, 'N'),
+    (piece_types::PAWN, 'P'),
+];
+
+struct TableFiles {
+    wdl_path: PathBuf,
+    dtz_path: Option<PathBuf>,
+    wdl: OnceLock<Option<Table>>,
+    dtz: OnceLock<Option<Table>>,
+}
+
+/// The tables found in a set of directories, keyed by name.
+pub(crate) struct Tablebases {
+    tables: HashMap<String, TableFiles>,
+    /// The largest number of pieces of a WDL table.
+    cardinality: usize,
+}
+
+impl Tablebases {
+    /// Scans directories separated by `:`, or `;` on Windows. A table without a WDL file is ignored.
+    pub(crate) fn new(paths: &str) -> Self {
+        let mut wdl_paths = HashMap::new();
+        let mut dtz_paths = HashMap::new();
+
+        for dir in paths.split(PATH_SEPARATOR).filter(|dir| !dir.is_empty()) {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+
+            for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+                let (Some(name), Some(extension)) = (path.file_stem(), path.extension()) else {
+                    continue;
+                };
+                let Some(name) = name.to_str().filter(|name| is_valid_name(name)) else {
+                    continue;
+                };
+
+                match extension.to_str() {
+                    Some("rtbw") => wdl_paths.insert(name.to_string(), path.clone()),
+                    Some("rtbz") => dtz_paths.insert(name.to_string(), path.clone()),
+                    _ => None,
+                };
+            }
+        }
+
+        let cardinality = wdl_paths
+            .keys()
+            .map(|name| name.len() - 1)
+            .max()
+            .unwrap_or(0);
+        let tables = wdl_paths
+            .into_iter()
+            .map(|(name, wdl_path)| {
+                let files = TableFiles {
+                    dtz_path: dtz_paths.remove(&name),
+                    wdl_path,
+                    wdl: OnceLock::new(),
+                    dtz: OnceLock::new(),
+                };
+                (name, files)
+            })
+            .collect();
+
+        Self {
+            tables,
+            cardinality,
+        }
+    }
+
+    /// The number of WDL tables found.
+    pub(crate) fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub(crate) const fn cardinality(&self) -> usize {
+        self.cardinality
+    }
+
+    /// Tables don't have castling rights and cover positions up to the cardinality.
+    pub(crate) const fn can_probe(&self, pos: &Position) -> bool {
+        pos.get_castling_rights() == 0 && pos.piece_count() as usize <= self.cardinality
+    }
+
+    /// Returns `None` if the position isn't covered or a table can't be read.
+    pub(crate) fn probe_wdl(&self, pos: &mut Position) -> Option<Wdl> {
+        if !self.can_probe(pos) {
+            return None;
+        }
+
+        self.search_wdl(pos, false).map(|(wdl, _)| wdl)
+    }
+
+    /// The number of plies until the next capture or pawn move in an optimal game, positive when
+    /// the side to move wins and 0 for draws. A cursed win or a blessed loss is off by 100.
+    /// Returns `None` if the position isn't covered or a table can't be read.
+    pub(crate) fn probe_dtz(&self, pos: &mut Position) -> Option<i32> {
+        if !self.can_probe(pos) {
+            return None;
+        }
+
+        self.dtz(pos)
+    }
+
+    /// Ranks the legal moves of a position: the higher the rank, the better the move, winning
+    /// moves that don't run into the fifty-move rule sharing the rank `MAX_DTZ`.
+    /// Returns `None` if the position isn't covered or a table can't be read.
+    pub(crate) fn rank_root_moves(&self, pos: &mut Position) -> Option<Vec<(Move, i32)>> {
+        if !self.can_probe(pos) {
+            return None;
+        }
+
+        let half_move_clock = pos.half_move_clock() as i32;
+        let undo_info = pos.undo_info();
+        let mut ranks = Vec::new();
+
+        for &mv in pos.legal_moves().as_slice() {
+            pos.play_move(mv);
+
+            let dtz = if pos.half_move_clock() == 0 {
+                self.search_wdl(pos, false)
+                    .map(|(wdl, _)| dtz_before_zeroing(-wdl))
+            } else if pos.rep_count() >= 2 {
+                Some(0)
+            } else {
+                self.dtz(pos).map(|dtz| -dtz - dtz.signum())
+            };
+            let is_mate = pos.is_check() && pos.legal_moves().is_empty();
+            pos.undo_move(mv, undo_info);
+
+            let dtz = ternary!(is_mate, 1, dtz?);
+            let rank = match dtz {
+                1.. if dtz + half_move_clock <= 99 => MAX_DTZ,
+                1.. => MAX_DTZ - (dtz + half_move_clock),
+                0 => 0,
+                _ if -dtz * 2 + half_move_clock < 100 => -MAX_DTZ,
+                _ => -MAX_DTZ + (-dtz + half_move_clock),
+            };
+            ranks.push((mv, rank));
+        }
+
+        Some(ranks)
+    }
+
+    /// Probes the WDL table, first trying the captures, and the pawn moves if `check_zeroing` is
+    /// set, since tables may store wrong values when the best move is one of them.
+    /// Also returns whether the best move is a capture or a pawn move.
+    fn search_wdl(&self, pos: &mut Position, check_zeroing: bool) -> Option<(Wdl, bool)> {
+        let moves = pos.legal_moves();
+        let undo_info = pos.undo_info();
+        let mut best_wdl = wdl::LOSS;
+        let mut nb_zeroing_moves = 0;
+
+        for &mv in moves.as_slice() {
+            if !is_capture(mv)
+                && (!check_zeroing || pieces::type_of(src_piece(mv)) != piece_types::PAWN)
+            {
+                continue;
+            }
+
+            nb_zeroing_moves += 1;
+            pos.play_move(mv);
+            let result = self.search_wdl(pos, false);
+            pos.undo_move(mv, undo_info);
+
+            let wdl = -result?.0;
+
+            if wdl > best_wdl {
+                best_wdl = wdl;
+
+                if wdl >= wdl::WIN {
+                    return Some((wdl, true));
+                }
+            }
+        }
+
+        let has_no_other_moves = nb_zeroing_moves > 0 && nb_zeroing_moves == moves.len();
+        let wdl = match has_no_other_moves {
+            true => best_wdl,
+            false => match self.probe_table(pos, Kind::Wdl, wdl::DRAW)? {
+                Probe::Value(wdl) => wdl,
+                Probe::ChangeSide => return None,
+            },
+        };
+
+        if best_wdl >= wdl {
+            return Some((best_wdl, best_wdl > wdl::DRAW || has_no_other_moves));
+        }
+
+        Some((wdl, false))
+    }
+
+    fn dtz(&self, pos: &mut Position) -> Option<i32> {
+        let (wdl, is_zeroing_best) = self.search_wdl(pos, true)?;
+
+        if wdl == wdl::DRAW {
+            return Some(0);
+        }
+
+        if is_zeroing_best {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        if let Probe::Value(dtz) = self.probe_table(pos, Kind::Dtz, wdl)? {
+            let is_cursed = wdl == wdl::CURSED_WIN || wdl == wdl::BLESSED_LOSS;
+            return Some((dtz + ternary!(is_cursed, 100, 0)) * wdl.signum());
+        }
+
+        // the table only stores the other side to move, so one more ply is searched
+        let undo_info = pos.undo_info();
+        let mut min_dtz = i32::MAX;
+
+        for &mv in pos.legal_moves().as_slice() {
+            let is_zeroing = is_capture(mv) || pieces::type_of(src_piece(mv)) == piece_types::PAWN;
+            pos.play_move(mv);
+
+            let dtz = match is_zeroing {
+                true => self
+                    .search_wdl(pos, false)
+                    .map(|(wdl, _)| -dtz_before_zeroing(wdl)),
+                false => self.dtz(pos).map(|dtz| -dtz),
+            };
+            let is_mate = pos.is_check() && pos.legal_moves().is_empty();
+            pos.undo_move(mv, undo_info);
+
+            let mut dtz = dtz?;
+
+            if dtz == 1 && is_mate {
+                min_dtz = 1;
+            }
+
+            if !is_zeroing {
+                dtz += dtz.signum();
+            }
+
+            if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                min_dtz = dtz;
+            }
+        }
+
+        Some(ternary!(min_dtz == i32::MAX, -1, min_dtz))
+    }
+
+    /// Finds the table of the material of the position, whose stronger side may be Black.
+    fn probe_table(&self, pos: &Position, kind: Kind, wdl: Wdl) -> Option<Probe> {
+        // only the kings are left
+        if pos.piece_count() == 2 {
+            return Some(Probe::Value(wdl::DRAW));
+        }
+
+        let white = material_name(pos, colors::WHITE);
+        let black = material_name(pos, colors::BLACK);
+        let (files, name, is_flipped) = match self.tables.get(&format!("{}v{}", white, black)) {
+            Some(files) => (files, format!("{}v{}", white, black), false),
+            None => {
+                let name = format!("{}v{}", black, white);
+                (self.tables.get(&name)?, name, true)
+            }
+        };
+
+        let table = match kind {
+            Kind::Wdl => files
+                .wdl
+                .get_or_init(|| load_table(&files.wdl_path, &name, kind)),
+            Kind::Dtz => files.dtz.get_or_init(|| {
+                files
+                    .dtz_path
+                    .as_ref()
+                    .and_then(|path| load_table(path, &name, kind))
+            }),
+        };
+
+        Some(table.as_ref()?.probe(pos, is_flipped, wdl))
+    }
+}
+
+fn load_table(path: &Path, name: &str, kind: Kind) -> Option<Table> {
+    Table::parse(fs::read(path).ok()?, name, kind)
+}
+
+/// The DTZ of a position whose best move is a capture or a pawn move.
+const fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        wdl::WIN => 1,
+        wdl::CURSED_WIN => 101,
+        wdl::BLESSED_LOSS => -101,
+        wdl::LOSS => -1,
+        _ => 0,
+    }
+}
+
+/// The pieces of one side as they appear in table names, e.g. `KRP`.
+fn material_name(pos: &Position, color: usize) -> String {
+    PIECE_INITIALS
+        .iter()
+        .map(|&(piece_type, initial)| {
+            let count = pos.piece_occupancy2(piece_type, color).count_ones();
+            initial.to_string().repeat(count as usize)
+        })
+        .collect()
+}
+
+/// Names like `KRPvKR`: each side has a king first and the pieces in the order of
+/// `PIECE_INITIALS`.
+fn is_valid_name(name: &str) -> bool {
+    let Some((white, black)) = name.split_once('v') else {
+        return false;
+    };
+
+    let is_valid_side = |side: &str| {
+        side.strip_prefix('K').is_some_and(|pieces| {
+            let order = |initial| PIECE_INITIALS.iter().position(|&(_, ch)| ch == initial);
+            let orders = pieces.chars().map(order).collect::<Option<Vec<_>>>();
+            orders.is_some_and(|orders| orders.is_sorted() && !orders.contains(&0))
+        })
+    };
+
+    is_valid_side(white) && is_valid_side(black) && white.len() + black.len() <= MAX_PIECES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_tablebases() -> Tablebases {
+        Tablebases {
+            tables: HashMap::new(),
+            cardinality: 5,
+        }
+    }
+
+    #[test]
+    fn names() {
+        let pos = Position::from_fen("8/8/4k3/3r4/8/2PRK3/8/8 w - - 0 1").unwrap();
+
+        assert_eq!(material_name(&pos, colors::WHITE), "KRP");
+        assert_eq!(material_name(&pos, colors::BLACK), "KR");
+        assert!(is_valid_name("KRPvKR"));
+        assert!(is_valid_name("KQQvKRB"));
+        assert!(!is_valid_name("KPRvKR"));
+        assert!(!is_valid_name("KRvKK"));
+        assert!(!is_valid_name("KRvR"));
+        assert!(!is_valid_name("KQQQvKQQQ"));
+    }
+
+    #[test]
+    fn kings_only() {
+        let tablebases = empty_tablebases();
+        let mut pos = Position::from_fen("8/8/4k3/8/8/2K5/8/8 w - - 0 1").unwrap();
+
+        assert_eq!(tablebases.probe_wdl(&mut pos), Some(wdl::DRAW));
+        assert_eq!(tablebases.probe_dtz(&mut pos), Some(0));
+    }
+
+    #[test]
+    fn missing_tables() {
+        let tablebases = empty_tablebases();
+        let mut krk = Position::from_fen("8/8/4k3/8/8/2K5/8/7R w - - 0 1").unwrap();
+        let mut castling = Position::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+
+        assert_eq!(tablebases.probe_wdl(&mut krk), None);
+        assert_eq!(tablebases.rank_root_moves(&mut krk), None);
+        assert!(!tablebases.can_probe(&castling));
+        assert_eq!(tablebases.probe_wdl(&mut castling), None);
+    }
+
+    #[test]
+    fn only_captures() {
+        // the king must take the rook, so the KRvK table isn't needed
+        let tablebases = empty_tablebases();
+        let mut pos = Position::from_fen("8/8/8/8/8/8/1r6/K6k w - - 0 1").unwrap();
+
+        assert_eq!(tablebases.probe_wdl(&mut pos), Some(wdl::DRAW));
+    }
+
+    #[test]
+    fn corrupt_files() {
+        let dir = std::env::temp_dir().join(format!("syzygy-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("KRvK.rtbw"), [0x71, 0xE8, 0x23, 0x5D, 0, 1]).unwrap();
+        fs::write(dir.join("KQvK.rtbw"), b"not a table").unwrap();
+        fs::write(dir.join("KQvRK.rtbw"), b"badly named").unwrap();
+
+        let tablebases = Tablebases::new(dir.to_str().unwrap());
+        let mut krk = Position::from_fen("8/8/4k3/8/8/2K5/8/7R b - - 0 1").unwrap();
+        let mut kqk = Position::from_fen("8/8/4k3/8/8/2K5/8/7Q b - - 0 1").unwrap();
+
+        assert_eq!(tablebases.len(), 2);
+        assert_eq!(tablebases.cardinality(), 3);
+        assert_eq!(tablebases.probe_wdl(&mut krk), None);
+        assert_eq!(tablebases.probe_wdl(&mut kqk), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn single_value_table() {
+        // KRvK with every position won by the side to move with the rook and lost otherwise
+        let mut bytes = vec![0x71, 0xE8, 0x23, 0x5D, 0x01, 0x00, 0xE6, 0xC4, 0x6E, 0x00];
+        bytes.extend([0x80, 4, 0x80, 0]);
+        bytes.resize(64, 0);
+
+        let mut tables = HashMap::new();
+        let files = TableFiles {
+            wdl_path: PathBuf::new(),
+            dtz_path: None,
+            wdl: OnceLock::from(Table::parse(bytes, "KRvK", Kind::Wdl)),
+            dtz: OnceLock::new(),
+        };
+        tables.insert("KRvK".to_string(), files);
+        let tablebases = Tablebases {
+            tables,
+            cardinality: 3,
+        };
+
+        let probe = |fen| tablebases.probe_wdl(&mut Position::from_fen(fen).unwrap());
+
+        assert_eq!(probe("8/8/4k3/8/8/2K5/8/7R w - - 0 1"), Some(wdl::WIN));
+        assert_eq!(probe("8/8/4k3/8/8/2K5/8/7R b - - 0 1"), Some(wdl::LOSS));
+        assert_eq!(probe("8/8/4k3/8/8/2K5/8/7r b - - 0 1"), Some(wdl::WIN));
+        // the rook can be taken
+        assert_eq!(probe("k7/8/8/8/8/8/6Kr/8 w - - 0 1"), Some(wdl::DRAW));
+    }
+
+    #[test]
+    fn krvk_fixture() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/src/syzygy/fixtures");
+        let tablebases = Tablebases::new(dir);
+
+        if tablebases.len() == 0 {
+            eprintln!("no tables in {}, see its README", dir);
+            return;
+        }
+
+        let wdl = |fen| tablebases.probe_wdl(&mut Position::from_fen(fen).unwrap());
+        let dtz = |fen| tablebases.probe_dtz(&mut Position::from_fen(fen).unwrap());
+
+        assert_eq!(wdl("8/8/4k3/8/8/2K5/8/7R w - - 0 1"), Some(wdl::WIN));
+        assert_eq!(wdl("8/8/4k3/8/8/2K5/8/7R b - - 0 1"), Some(wdl::LOSS));
+        assert_eq!(wdl("8/8/4k3/8/8/2K5/8/7r b - - 0 1"), Some(wdl::WIN));
+        // the rook can be taken
+        assert_eq!(wdl("k7/8/8/8/8/8/6Kr/8 w - - 0 1"), Some(wdl::DRAW));
+
+        // Ra8#
+        assert_eq!(dtz("7k/8/6K1/8/8/8/8/R7 w - - 0 1"), Some(1));
+        assert!(dtz("8/8/4k3/8/8/2K5/8/7R w - - 0 1").is_some_and(|dtz| dtz > 1));
+        assert!(dtz("8/8/4k3/8/8/2K5/8/7R b - - 0 1").is_some_and(|dtz| dtz < 0));
+    }
+
+    #[test]
+    #[ignore = "needs the 3 and 4 piece tables, run with `SYZYGY_PATH=<dir> cargo test -- --ignored`"]
+    fn real_tables() {
+        let path = std::env::var("SYZYGY_PATH").expect("SYZYGY_PATH is not set");
+        let tablebases = Tablebases::new(&path);
+        let wdl = |fen| tablebases.probe_wdl(&mut Position::from_fen(fen).unwrap());
+        let dtz = |fen| tablebases.probe_dtz(&mut Position::from_fen(fen).unwrap());
+
+        assert!(tablebases.cardinality() >= 4);
+
+        assert_eq!(wdl("8/8/4k3/8/8/2K5/8/7R w - - 0 1"), Some(wdl::WIN));
+        assert_eq!(wdl("8/8/4k3/8/8/2K5/8/7R b - - 0 1"), Some(wdl::LOSS));
+        assert_eq!(wdl("8/8/4k3/8/8/2K5/8/7B w - - 0 1"), Some(wdl::DRAW));
+        assert_eq!(wdl("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Some(wdl::WIN));
+        assert_eq!(wdl("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Some(wdl::LOSS));
+        // stalemate
+        assert_eq!(wdl("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), Some(wdl::DRAW));
+        assert_eq!(wdl("8/8/3k4/8/8/3K4/r7/7R w - - 0 1"), Some(wdl::DRAW));
+
+        // Qa8#
+        assert_eq!(dtz("7k/8/6K1/8/8/8/8/Q7 w - - 0 1"), Some(1));
+        assert!(dtz("8/8/4k3/8/8/2K5/8/7R w - - 0 1").is_some_and(|dtz| dtz > 0));
+        assert!(dtz("8/8/4k3/8/8/2K5/8/7R b - - 0 1").is_some_and(|dtz| dtz < 0));
+        assert_eq!(dtz("8/8/3k4/8/8/3K4/r7/7R w - - 0 1"), Some(0));
+    }
+}
@@ -0,0 +1,712 @@
+use crate::{
+    game::{
+        board::{NB_SQUARES, colors},
+        position::Position,
+    },
+    macros::ternary,
+    syzygy::{Wdl, encoding::*, wdl},
+};
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+const MAX_PIECES: usize = 7;
+
+/// Pieces are numbered 1 to 6 from pawn to king, with 8 added for the second side of the table.
+const TB_BLACK: u8 = 8;
+/// Square offset mirroring the board between the two sides.
+const TB_FLIP_SQUARES: usize = 56;
+
+mod flags {
+    /// The side to move a DTZ table is stored for.
+    pub(super) const STM: u8 = 1;
+    pub(super) const MAPPED: u8 = 2;
+    pub(super) const WIN_PLIES: u8 = 4;
+    pub(super) const LOSS_PLIES: u8 = 8;
+    pub(super) const WIDE: u8 = 16;
+    pub(super) const SINGLE_VALUE: u8 = 128;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Kind {
+    Wdl,
+    Dtz,
+}
+
+/// The result of probing a table.
+pub(super) enum Probe {
+    /// A WDL score, or a number of plies for a DTZ table.
+    Value(i32),
+    /// DTZ tables only store one side to move and the position has the other one.
+    ChangeSide,
+}
+
+/// The compressed values of a table for one side to move and one file of the leading pawn.
+/// Offsets point into the bytes of the table file.
+#[derive(Default, Clone)]
+struct PairsData {
+    flags: u8,
+    block_size: usize,
+    /// A sparse index entry is stored about every `span` values.
+    span: u64,
+    num_blocks: usize,
+    max_sym_len: u8,
+    /// Also the value of every position when the table holds a single value.
+    min_sym_len: u8,
+    lowest_sym: usize,
+    btree: usize,
+    block_lengths: usize,
+    block_lengths_size: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    data: usize,
+    /// `base64[l]` is the lowest symbol of length `l + min_sym_len`, padded to 64 bits.
+    base64: Vec<u64>,
+    /// The number of values minus one each symbol expands to.
+    symlen: Vec<u8>,
+    /// The order in which pieces are encoded, which also defines the groups.
+    pieces: [u8; MAX_PIECES],
+    group_idx: [u64; MAX_PIECES + 1],
+    /// Zero-terminated lengths of the groups of pieces, e.g. KRvKN gives 3 and 1.
+    group_len: [usize; MAX_PIECES + 1],
+    /// Where the DTZ values of wins, losses, cursed wins and blessed losses are remapped.
+    map_idx: [u16; 4],
+}
+
+/// A WDL or DTZ table loaded in memory, named after its material like `KRPvKR`.
+/// The first side of the name is White in the table and the stronger side of the position.
+pub(super) struct Table {
+    kind: Kind,
+    bytes: Vec<u8>,
+    nb_pieces: usize,
+    has_pawns: bool,
+    /// At least one side has a piece other than the king with no twin.
+    has_unique_pieces: bool,
+    /// The pawns of the leading color, then those of the other one.
+    pawn_counts: [usize; 2],
+    /// Both sides have the same material.
+    is_symmetric: bool,
+    /// Indexed by the file of the leading pawn, then by side to move for WDL tables.
+    pairs: Vec<Vec<PairsData>>,
+    /// The start of the DTZ value maps.
+    map: usize,
+}
+
+impl Table {
+    /// Returns `None` if the file is truncated or doesn't match its name.
+    pub(super) fn parse(bytes: Vec<u8>, name: &str, kind: Kind) -> Option<Self> {
+        let magic = ternary!(kind == Kind::Wdl, WDL_MAGIC, DTZ_MAGIC);
+
+        if bytes.get(..4)? != magic {
+            return None;
+        }
+
+        let (white, black) = name.split_once('v')?;
+        let count = |side: &str, initial: char| side.chars().filter(|&ch| ch == initial).count();
+        let pawns = [count(white, 'P'), count(black, 'P')];
+        // the side with fewer pawns leads, which compresses better
+        let is_white_leading = pawns[1] == 0 || (pawns[0] > 0 && pawns[1] >= pawns[0]);
+
+        let mut table = Self {
+            kind,
+            bytes,
+            nb_pieces: white.len() + black.len(),
+            has_pawns: pawns[0] + pawns[1] > 0,
+            has_unique_pieces: [white, black]
+                .iter()
+                .any(|side| "QRBNP".chars().any(|initial| count(side, initial) == 1)),
+            pawn_counts: ternary!(is_white_leading, pawns, [pawns[1], pawns[0]]),
+            is_symmetric: white == black,
+            pairs: Vec::new(),
+            map: 0,
+        };
+
+        if table.nb_pieces > MAX_PIECES {
+            return None;
+        }
+
+        (table.pairs, table.map) = table.read_pairs()?;
+        Some(table)
+    }
+
+    fn read_pairs(&self) -> Option<(Vec<Vec<PairsData>>, usize)> {
+        const SPLIT: u8 = 1;
+        const HAS_PAWNS: u8 = 2;
+
+        let bytes = &self.bytes;
+        let header = *bytes.get(4)?;
+
+        if (header & HAS_PAWNS != 0) != self.has_pawns || (header & SPLIT != 0) == self.is_symmetric
+        {
+            return None;
+        }
+
+        let nb_sides = ternary!(self.kind == Kind::Wdl && !self.is_symmetric, 2, 1);
+        let nb_files = ternary!(self.has_pawns, 4, 1);
+        let has_both_pawns = self.has_pawns && self.pawn_counts[1] > 0;
+        let mut pairs = vec![vec![PairsData::default(); nb_sides]; nb_files];
+        let mut i = 5;
+
+        for (file, file_pairs) in pairs.iter_mut().enumerate() {
+            let order_byte = |offset: usize, shift: u8| {
+                bytes
+                    .get(i + offset)
+                    .map(|&byte| (byte >> shift & 0xF) as usize)
+            };
+            let order = [
+                [
+                    order_byte(0, 0)?,
+                    ternary!(has_both_pawns, order_byte(1, 0)?, 0xF),
+                ],
+                [
+                    order_byte(0, 4)?,
+                    ternary!(has_both_pawns, order_byte(1, 4)?, 0xF),
+                ],
+            ];
+            i += 1 + has_both_pawns as usize;
+
+            for k in 0..self.nb_pieces {
+                let byte = *bytes.get(i)?;
+
+                for (side, d) in file_pairs.iter_mut().enumerate() {
+                    d.pieces[k] = ternary!(side == 0, byte & 0xF, byte >> 4);
+                }
+
+                i += 1;
+            }
+
+            for (side, d) in file_pairs.iter_mut().enumerate() {
+                self.set_groups(d, order[side], file)?;
+            }
+        }
+
+        i += i & 1;
+
+        for d in pairs.iter_mut().flatten() {
+            i = set_sizes(d, bytes, i)?;
+        }
+
+        let map = i;
+
+        if self.kind == Kind::Dtz {
+            i = set_dtz_map(&mut pairs, bytes, i)?;
+        }
+
+        for d in pairs.iter_mut().flatten() {
+            d.sparse_index = i;
+            i += d.sparse_index_size * 6;
+        }
+
+        for d in pairs.iter_mut().flatten() {
+            d.block_lengths = i;
+            i += d.block_lengths_size * 2;
+        }
+
+        for d in pairs.iter_mut().flatten() {
+            // blocks are aligned on 64 bytes
+            i = (i + 0x3F) & !0x3F;
+            d.data = i;
+            i += d.num_blocks * d.block_size;
+        }
+
+        ternary!(i <= bytes.len(), Some((pairs, map)), None)
+    }
+
+    /// Splits the pieces into groups and computes the factor each group's index is multiplied by.
+    /// The leading group is either the leading pawns or the kings with, when there are unique
+    /// pieces, a third one. `order` tells in which order the groups are encoded.
+    fn set_groups(&self, d: &mut PairsData, order: [usize; 2], file: usize) -> Option<()> {
+        let mut first_len = ternary!(self.has_pawns, 0, ternary!(self.has_unique_pieces, 3, 2));
+        let mut n = 0;
+        d.group_len[0] = 1;
+
+        for i in 1..self.nb_pieces {
+            first_len -= 1;
+
+            if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+                d.group_len[n] += 1;
+            } else {
+                n += 1;
+                d.group_len[n] = 1;
+            }
+        }
+
+        n += 1;
+        d.group_len[n] = 0;
+
+        let has_both_pawns = self.has_pawns && self.pawn_counts[1] > 0;
+        let mut next = ternary!(has_both_pawns, 2, 1);
+        let mut free_squares =
+            NB_SQUARES - d.group_len[0] - ternary!(has_both_pawns, d.group_len[1], 0);
+        let mut idx = 1u64;
+        let mut k = 0;
+
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                d.group_idx[0] = idx;
+                idx *= match (self.has_pawns, self.has_unique_pieces) {
+                    (true, _) => *LEAD_PAWNS_SIZE.get(d.group_len[0])?.get(file)?,
+                    (false, true) => NB_UNIQUE_TRIPLES,
+                    (false, false) => NB_KING_PAIRS,
+                };
+            } else if k == order[1] {
+                d.group_idx[1] = idx;
+                idx *= BINOMIAL.get(d.group_len[1])?[48 - d.group_len[0]];
+            } else {
+                d.group_idx[next] = idx;
+                idx *= BINOMIAL.get(d.group_len[next])?[free_squares];
+                free_squares -= d.group_len[next];
+                next += 1;
+            }
+
+            k += 1;
+        }
+
+        d.group_idx[n] = idx;
+        Some(())
+    }
+
+    fn pairs_data(&self, stm: usize, file: usize) -> &PairsData {
+        let sides = &self.pairs[ternary!(self.has_pawns, file, 0)];
+        &sides[stm.min(sides.len() - 1)]
+    }
+
+    /// Encodes the position into an index of the table and reads its value.
+    /// `is_flipped` tells that Black has the material of the first side of the table.
+    /// `wdl` is the result of the position, needed to read DTZ tables.
+    pub(super) fn probe(&self, pos: &Position, is_flipped: bool, wdl: Wdl) -> Probe {
+        let is_symmetric_black_to_move =
+            self.is_symmetric && pos.get_active_color() == colors::BLACK;
+        let flip = is_symmetric_black_to_move || is_flipped;
+        let flip_color = ternary!(flip, TB_BLACK, 0);
+        let flip_squares = ternary!(flip, TB_FLIP_SQUARES, 0);
+        let stm = flip as usize ^ pos.get_active_color();
+
+        let mut squares = [0; MAX_PIECES];
+        let mut pieces = [0; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut file = 0;
+
+        // the leading pawns come first in the tables of every file and their color is the
+        // reference one
+        if self.has_pawns {
+            let lead_piece = self.pairs[0][0].pieces[0] ^ flip_color;
+            lead_pawns = pos.pawn_occupancy(ternary!(
+                lead_piece & TB_BLACK != 0,
+                colors::BLACK,
+                colors::WHITE
+            ));
+
+            for sq in squares_of(lead_pawns) {
+                squares[size] = sq ^ flip_squares;
+                size += 1;
+            }
+
+            // the leading pawn is the one nearest to an edge
+            let lead = (0..size)
+                .rev()
+                .max_by_key(|&i| MAP_PAWNS[squares[i]])
+                .unwrap();
+            squares.swap(0, lead);
+            file = edge_distance(squares[0] % 8);
+        }
+
+        let nb_lead_pawns = size;
+        let d = self.pairs_data(stm, file);
+
+        if self.kind == Kind::Dtz
+            && (d.flags & flags::STM) as usize != stm
+            && (!self.is_symmetric || self.has_pawns)
+        {
+            return Probe::ChangeSide;
+        }
+
+        for sq in squares_of(pos.full_occupancy() ^ lead_pawns) {
+            squares[size] = sq ^ flip_squares;
+            pieces[size] = to_tb_piece(pos.get_piece(sq)) ^ flip_color;
+            size += 1;
+        }
+
+        // reorders the pieces the way the table encodes them
+        for i in nb_lead_pawns..size - 1 {
+            if let Some(j) = (i + 1..size).find(|&j| d.pieces[i] == pieces[j]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        if squares[0] % 8 > 3 {
+            squares[..size]
+                .iter_mut()
+                .for_each(|sq| *sq = flip_file(*sq));
+        }
+
+        let idx = ternary!(
+            self.has_pawns,
+            encode_lead_pawns(&mut squares[..nb_lead_pawns]),
+            self.encode_lead_pieces(d, &mut squares[..size])
+        );
+        let idx = idx * d.group_idx[0]
+            + encode_remaining(
+                d,
+                &mut squares[..size],
+                self.has_pawns && self.pawn_counts[1] > 0,
+            );
+        let value = self.decompress(d, idx);
+
+        Probe::Value(match self.kind {
+            Kind::Wdl => value as i32 - 2,
+            Kind::Dtz => self.map_score(file, value, wdl),
+        })
+    }
+
+    /// Mirrors the board so that the leading piece is in the a1-d1-d4 triangle and the first piece
+    /// of the leading group that isn't on the a1-h8 diagonal is below it, then encodes the group.
+    fn encode_lead_pieces(&self, d: &PairsData, squares: &mut [usize]) -> u64 {
+        if squares[0] / 8 > 3 {
+            squares.iter_mut().for_each(|sq| *sq = flip_rank(*sq));
+        }
+
+        if let Some(i) = (0..d.group_len[0]).find(|&i| off_diagonal(squares[i]) != 0)
+            && off_diagonal(squares[i]) > 0
+        {
+            squares[i..]
+                .iter_mut()
+                .for_each(|sq| *sq = flip_diagonal(*sq));
+        }
+
+        if !self.has_unique_pieces {
+            return MAP_KK[MAP_A1D1D4[squares[0]] as usize][squares[1]];
+        }
+
+        let (sq0, sq1, sq2) = (squares[0], squares[1], squares[2]);
+        let adjust1 = (sq1 > sq0) as u64;
+        let adjust2 = (sq2 > sq0) as u64 + (sq2 > sq1) as u64;
+        let rank = |sq: usize| (sq / 8) as u64;
+
+        if off_diagonal(sq0) != 0 {
+            (MAP_A1D1D4[sq0] * 63 + (sq1 as u64 - adjust1)) * 62 + sq2 as u64 - adjust2
+        } else if off_diagonal(sq1) != 0 {
+            (6 * 63 + rank(sq0) * 28 + MAP_B1H1H7[sq1]) * 62 + sq2 as u64 - adjust2
+        } else if off_diagonal(sq2) != 0 {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + rank(sq0) * 7 * 28
+                + (rank(sq1) - adjust1) * 28
+                + MAP_B1H1H7[sq2]
+        } else {
+            6 * 63 * 62
+                + 4 * 28 * 62
+                + 4 * 7 * 28
+                + rank(sq0) * 7 * 6
+                + (rank(sq1) - adjust1) * 6
+                + (rank(sq2) - adjust2)
+        }
+    }
+
+    /// Finds the value stored at an index: the block holding it is located through the sparse
+    /// index, then its Huffman symbols are read up to the one covering the index and that
+    /// symbol is expanded through the pairs it was built from.
+    fn decompress(&self, d: &PairsData, idx: u64) -> u32 {
+        if d.flags & flags::SINGLE_VALUE != 0 {
+            return d.min_sym_len as u32;
+        }
+
+        let bytes = &self.bytes;
+        let k = (idx / d.span) as usize;
+        let mut block = read_u32_le(bytes, d.sparse_index + 6 * k) as usize;
+        let mut offset = read_u16_le(bytes, d.sparse_index + 6 * k + 4) as i64;
+        offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+
+        let block_length = |block: usize| read_u16_le(bytes, d.block_lengths + 2 * block) as i64;
+
+        while offset < 0 {
+            block -= 1;
+            offset += block_length(block) + 1;
+        }
+
+        while offset > block_length(block) {
+            offset -= block_length(block) + 1;
+            block += 1;
+        }
+
+        let min_sym_len = d.min_sym_len as usize;
+        let mut ptr = d.data + block * d.block_size;
+        let mut buf64 = read_u64_be(bytes, ptr);
+        let mut buf64_size = 64;
+        let mut sym;
+        ptr += 8;
+
+        loop {
+            let mut len = 0;
+
+            while buf64 < d.base64[len] {
+                len += 1;
+            }
+
+            sym = ((buf64 - d.base64[len]) >> (64 - len - min_sym_len)) as usize;
+            sym += read_u16_le(bytes, d.lowest_sym + 2 * len) as usize;
+
+            if offset < d.symlen[sym] as i64 + 1 {
+                break;
+            }
+
+            offset -= d.symlen[sym] as i64 + 1;
+            len += min_sym_len;
+            buf64 <<= len;
+            buf64_size -= len;
+
+            if buf64_size <= 32 {
+                buf64_size += 32;
+                buf64 |= (read_u32_be(bytes, ptr) as u64) << (64 - buf64_size);
+                ptr += 4;
+            }
+        }
+
+        while d.symlen[sym] != 0 {
+            let (left, right) = children(bytes, d, sym);
+
+            if offset < d.symlen[left] as i64 + 1 {
+                sym = left;
+            } else {
+                offset -= d.symlen[left] as i64 + 1;
+                sym = right;
+            }
+        }
+
+        children(bytes, d, sym).0 as u32
+    }
+
+    /// Turns a stored DTZ value into plies.
+    fn map_score(&self, file: usize, value: u32, wdl: Wdl) -> i32 {
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+
+        let d = &self.pairs[file][0];
+        let mut value = value as usize;
+
+        if d.flags & flags::MAPPED != 0 {
+            let idx = d.map_idx[WDL_MAP[(wdl + 2) as usize]] as usize + value;
+            value = ternary!(
+                d.flags & flags::WIDE != 0,
+                read_u16_le(&self.bytes, self.map + 2 * idx) as usize,
+                self.bytes.get(self.map + idx).copied().unwrap_or(0) as usize
+            );
+        }
+
+        let is_in_moves = match wdl {
+            wdl::WIN => d.flags & flags::WIN_PLIES == 0,
+            wdl::LOSS => d.flags & flags::LOSS_PLIES == 0,
+            wdl::CURSED_WIN | wdl::BLESSED_LOSS => true,
+            _ => false,
+        };
+
+        ternary!(is_in_moves, value as i32 * 2, value as i32) + 1
+    }
+}
+
+/// The square indices of a bitboard, in increasing order.
+fn squares_of(mut bb: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if bb == 0 {
+            return None;
+        }
+
+        let sq = bb.trailing_zeros() as usize;
+        bb &= bb - 1;
+        Some(sq)
+    })
+}
+
+/// The engine numbers pieces as `type << 1 | color` from pawn to king.
+const fn to_tb_piece(piece: usize) -> u8 {
+    ((piece >> 1) + 1) as u8 | ((piece & 1) as u8 * TB_BLACK)
+}
+
+/// The first pawn is the leading one, the others are encoded by increasing `MAP_PAWNS` value.
+fn encode_lead_pawns(squares: &mut [usize]) -> u64 {
+    let mut idx = LEAD_PAWN_IDX[squares.len()][squares[0]];
+    squares[1..].sort_by_key(|&sq| MAP_PAWNS[sq]);
+
+    for (i, &sq) in squares.iter().enumerate().skip(1) {
+        idx += BINOMIAL[i][MAP_PAWNS[sq] as usize];
+    }
+
+    idx
+}
+
+/// Encodes the groups after the leading one, each by the combination of its squares. A square
+/// is shifted down for every piece of the previous groups standing below it.
+fn encode_remaining(d: &PairsData, squares: &mut [usize], mut has_remaining_pawns: bool) -> u64 {
+    let mut idx = 0;
+    let mut start = d.group_len[0];
+    let mut next = 1;
+
+    while d.group_len[next] != 0 {
+        let end = start + d.group_len[next];
+        squares[start..end].sort();
+
+        let mut n = 0;
+
+        for i in start..end {
+            let sq = squares[i];
+            let adjust = squares[..start]
+                .iter()
+                .filter(|&&prev_sq| sq > prev_sq)
+                .count();
+            let pawn_adjust = ternary!(has_remaining_pawns, 8, 0);
+            n += BINOMIAL[i - start + 1][sq - adjust - pawn_adjust];
+        }
+
+        has_remaining_pawns = false;
+        idx += n * d.group_idx[next];
+        start = end;
+        next += 1;
+    }
+
+    idx
+}
+
+/// Reads the header of the compressed values and builds the canonical Huffman code.
+/// Returns the offset following it.
+fn set_sizes(d: &mut PairsData, bytes: &[u8], mut i: usize) -> Option<usize> {
+    d.flags = *bytes.get(i)?;
+    i += 1;
+
+    if d.flags & flags::SINGLE_VALUE != 0 {
+        d.min_sym_len = *bytes.get(i)?;
+        return Some(i + 1);
+    }
+
+    let header = bytes.get(i..i + 10)?;
+    let tb_size = d.group_idx[d.group_len.iter().position(|&len| len == 0)?];
+
+    d.block_size = 1 << header[0];
+    d.span = 1 << header[1];
+    d.sparse_index_size = tb_size.div_ceil(d.span) as usize;
+    d.num_blocks = read_u32_le(bytes, i + 3) as usize;
+    // padded so that the sparse index doesn't point out of range
+    d.block_lengths_size = d.num_blocks + header[2] as usize;
+    d.max_sym_len = header[7];
+    d.min_sym_len = header[8];
+    d.lowest_sym = i + 9;
+    i += 9;
+
+    if d.max_sym_len < d.min_sym_len {
+        return None;
+    }
+
+    let nb_lengths = (d.max_sym_len - d.min_sym_len) as usize + 1;
+    let lowest_sym = |len: usize| read_u16_le(bytes, d.lowest_sym + 2 * len) as u64;
+    d.base64 = vec![0; nb_lengths];
+
+    // longer symbols have lower values, so that base64[l] >= base64[l + 1] once padded
+    for len in (0..nb_lengths - 1).rev() {
+        d.base64[len] = (d.base64[len + 1] + lowest_sym(len) - lowest_sym(len + 1)) / 2;
+    }
+
+    for (len, base) in d.base64.iter_mut().enumerate() {
+        *base = base
+            .checked_shl((64 - len - d.min_sym_len as usize) as u32)
+            .unwrap_or(0);
+    }
+
+    i += nb_lengths * 2;
+    let nb_syms = read_u16_le(bytes, i) as usize;
+    i += 2;
+    d.btree = i;
+    d.symlen = vec![0; nb_syms];
+
+    let mut visited = vec![false; nb_syms];
+
+    for sym in 0..nb_syms {
+        if !visited[sym] {
+            d.symlen[sym] = set_symlen(d, bytes, sym, &mut visited);
+        }
+    }
+
+    Some(i + nb_syms * 3 + (nb_syms & 1))
+}
+
+/// The pair tree is acyclic, a symbol without a right child being a value.
+fn set_symlen(d: &mut PairsData, bytes: &[u8], sym: usize, visited: &mut [bool]) -> u8 {
+    visited[sym] = true;
+    let (left, right) = children(bytes, d, sym);
+
+    if right == 0xFFF || left >= d.symlen.len() || right >= d.symlen.len() {
+        return 0;
+    }
+
+    if !visited[left] {
+        d.symlen[left] = set_symlen(d, bytes, left, visited);
+    }
+
+    if !visited[right] {
+        d.symlen[right] = set_symlen(d, bytes, right, visited);
+    }
+
+    d.symlen[left].wrapping_add(d.symlen[right]).wrapping_add(1)
+}
+
+/// The two symbols a symbol expands to, stored on 12 bits each.
+fn children(bytes: &[u8], d: &PairsData, sym: usize) -> (usize, usize) {
+    let i = d.btree + 3 * sym;
+    let lr = [0, 1, 2].map(|j| bytes.get(i + j).copied().unwrap_or(0) as usize);
+
+    ((lr[1] & 0xF) << 8 | lr[0], lr[2] << 4 | lr[1] >> 4)
+}
+
+/// Reads where the DTZ values of each result are remapped, for every file.
+fn set_dtz_map(pairs: &mut [Vec<PairsData>], bytes: &[u8], mut i: usize) -> Option<usize> {
+    let map = i;
+
+    for d in pairs.iter_mut().map(|sides| &mut sides[0]) {
+        if d.flags & flags::MAPPED == 0 {
+            continue;
+        }
+
+        if d.flags & flags::WIDE != 0 {
+            i += i & 1;
+
+            for map_idx in &mut d.map_idx {
+                *map_idx = ((i - map) / 2 + 1) as u16;
+                i += 2 * read_u16_le(bytes, i) as usize + 2;
+            }
+        } else {
+            for map_idx in &mut d.map_idx {
+                *map_idx = (i - map + 1) as u16;
+                i += *bytes.get(i)? as usize + 1;
+            }
+        }
+    }
+
+    Some(i + (i & 1))
+}
+
+/// Reads past the end of the file give zeros, as blocks may be read a few bytes too far.
+fn read_bytes<const N: usize>(bytes: &[u8], i: usize) -> [u8; N] {
+    let mut buf = [0; N];
+
+    if let Some(slice) = bytes.get(i..) {
+        let len = slice.len().min(N);
+        buf[..len].copy_from_slice(&slice[..len]);
+    }
+
+    buf
+}
+
+fn read_u16_le(bytes: &[u8], i: usize) -> u16 {
+    u16::from_le_bytes(read_bytes(bytes, i))
+}
+
+fn read_u32_le(bytes: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes(read_bytes(bytes, i))
+}
+
+fn read_u32_be(bytes: &[u8], i: usize) -> u32 {
+    u32::from_be_bytes(read_bytes(bytes, i))
+}
+
+fn read_u64_be(bytes: &[u8], i: usize) -> u64 {
+    u64::from_be_bytes(read_bytes(bytes, i))
+}
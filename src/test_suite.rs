@@ -0,0 +1,204 @@
+use colored::Colorize;
+
+use crate::{
+    engine::{self, Context, Limits, Score, is_mate_score, moves_to_mate},
+    game::{
+        epd::{self, EPDRecord},
+        moves::{Move, NULL_MOVE, san},
+        position::Position,
+    },
+};
+
+/// Searches every position of an EPD file and prints whether the engine found the expected move.
+/// A position is solved when the move played is one of `bm`, none of `am` and, with `dm`,
+/// when the search sees the mate in at most that many moves. STS-style comments such as
+/// `c0 "Qg6=10, Nf7+=3"` award points to the move played, other positions are worth one point.
+pub(crate) fn run(epd_file: &str, limits: &Limits) {
+    let mut summary = Summary::default();
+    let mut ctx = Context::new(engine::DEFAULT_HASH_SIZE_MB);
+
+    for (i, line) in epd_file.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let epd = match epd::parse_epd(line) {
+            Ok(epd) => epd,
+            Err(error) => {
+                println!("line {}: {:?}", i + 1, error);
+                continue;
+            }
+        };
+
+        let name = epd.id.clone().unwrap_or(format!("line {}", i + 1));
+        let mut pos = Position::from_fen(&epd.fen).unwrap();
+
+        if pos.legal_moves().is_empty() {
+            println!("{} {} (no legal move)", name, "skipped".yellow());
+            continue;
+        }
+
+        // every position is searched from scratch
+        ctx.clear();
+        let (mv, score) = engine::run_with_context(&mut pos, &mut ctx, limits, false);
+
+        if mv == NULL_MOVE {
+            println!("{} {} (no move found)", name, "skipped".yellow());
+            continue;
+        }
+
+        let is_solved = is_solved(&epd, mv, score);
+        summary.add(mv, is_solved, award_points(&pos, &epd));
+        let san = san::to_san(&mut pos, mv);
+
+        let status = match is_solved {
+            true => "solved".green(),
+            false => "failed".red(),
+        };
+        println!(
+            "{} {} {} ({})",
+            name,
+            status,
+            san,
+            engine::to_centipawns(score)
+        );
+    }
+
+    println!(
+        "\nSolved {}/{}, score {}/{}",
+        summary.solved, summary.total, summary.points, summary.max_points
+    );
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Summary {
+    solved: u32,
+    total: u32,
+    points: u32,
+    max_points: u32,
+}
+
+impl Summary {
+    fn add(&mut self, mv: Move, is_solved: bool, move_points: Option<Vec<(Move, u32)>>) {
+        self.total += 1;
+        self.solved += is_solved as u32;
+
+        match move_points {
+            Some(move_points) => {
+                self.points += move_points
+                    .iter()
+                    .find(|&&(point_mv, _)| point_mv == mv)
+                    .map_or(0, |&(_, points)| points);
+                self.max_points += move_points
+                    .iter()
+                    .map(|&(_, points)| points)
+                    .max()
+                    .unwrap_or(0);
+            }
+            None => {
+                self.points += is_solved as u32;
+                self.max_points += 1;
+            }
+        };
+    }
+}
+
+fn is_solved(epd: &EPDRecord, mv: Move, score: Score) -> bool {
+    let is_direct_mate =
+        |moves: u32| is_mate_score(score) && score > 0 && moves_to_mate(score) <= moves as Score;
+
+    (epd.best_moves.is_empty() || epd.best_moves.contains(&mv))
+        && !epd.avoid_moves.contains(&mv)
+        && epd.direct_mate.is_none_or(is_direct_mate)
+}
+
+/// The moves a `c0` comment of the form `"Qg6=10, Nf7+=3"` gives points to.
+fn award_points(pos: &Position, epd: &EPDRecord) -> Option<Vec<(Move, u32)>> {
+    epd.comment
+        .as_ref()?
+        .split(',')
+        .map(|entry| {
+            let (san, points) = entry.trim().split_once('=')?;
+            Some((san::parse_san(pos, san).ok()?, points.parse::<u32>().ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::MATE_SCORE;
+
+    use super::*;
+
+    const WAC_001: &str = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; \
+        id \"WAC.001\"; c0 \"Qg6=10, Nf7+=3\"";
+
+    fn parse_move(epd: &EPDRecord, san: &str) -> Move {
+        san::parse_san(&Position::from_fen(&epd.fen).unwrap(), san).unwrap()
+    }
+
+    #[test]
+    fn best_and_avoid_moves() {
+        let epd = epd::parse_epd(WAC_001).unwrap();
+        assert!(is_solved(&epd, parse_move(&epd, "Qg6"), 0));
+        assert!(!is_solved(&epd, parse_move(&epd, "Nf7+"), 0));
+
+        let epd = epd::parse_epd("4k3/8/8/8/8/8/3P4/4K3 w - - am Kd1;").unwrap();
+        assert!(!is_solved(&epd, parse_move(&epd, "Kd1"), 0));
+        assert!(is_solved(&epd, parse_move(&epd, "d4"), 0));
+    }
+
+    #[test]
+    fn direct_mate() {
+        let epd = epd::parse_epd("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; dm 1;").unwrap();
+        let mv = parse_move(&epd, "Ra8#");
+
+        assert!(is_solved(&epd, mv, MATE_SCORE - 1));
+        assert!(!is_solved(&epd, mv, MATE_SCORE - 3));
+        assert!(!is_solved(&epd, mv, 900));
+        assert!(!is_solved(&epd, mv, -MATE_SCORE + 2));
+    }
+
+    #[test]
+    fn comment_points() {
+        let epd = epd::parse_epd(WAC_001).unwrap();
+        let pos = Position::from_fen(&epd.fen).unwrap();
+
+        assert_eq!(
+            award_points(&pos, &epd),
+            Some(vec![
+                (parse_move(&epd, "Qg6"), 10),
+                (parse_move(&epd, "Nf7+"), 3)
+            ])
+        );
+
+        for comment in ["Qg6", "Qg6=ten", "Qb3=10", "Qg6=10, Nf7+=3,"] {
+            let epd = epd::parse_epd(&WAC_001.replace("Qg6=10, Nf7+=3", comment)).unwrap();
+            assert_eq!(award_points(&pos, &epd), None, "{}", comment);
+        }
+
+        let epd = epd::parse_epd("4k3/8/8/8/8/8/3P4/4K3 w - - bm d4;").unwrap();
+        assert_eq!(award_points(&pos, &epd), None);
+    }
+
+    #[test]
+    fn totals() {
+        let epd = epd::parse_epd(WAC_001).unwrap();
+        let pos = Position::from_fen(&epd.fen).unwrap();
+        let mut summary = Summary::default();
+
+        summary.add(parse_move(&epd, "Nf7+"), false, award_points(&pos, &epd));
+        summary.add(parse_move(&epd, "Qg6"), true, None);
+        summary.add(parse_move(&epd, "Qg6"), false, None);
+
+        assert_eq!(
+            summary,
+            Summary {
+                solved: 1,
+                total: 3,
+                points: 4,
+                max_points: 12,
+            }
+        );
+    }
+}